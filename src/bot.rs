@@ -0,0 +1,280 @@
+use crate::{
+    game::VisibleEntities,
+    map::{
+        player::Player,
+        point::{AsPoint, Point},
+    },
+    utils::{consts::Mass, id::PlayerID, rules::EatRule},
+};
+
+// how far a bot looks for other players, scaled by its vision
+const AWARENESS_RANGE: f32 = 1_000.0;
+// a threat this close to a virus makes it worth feeding the virus
+const VIRUS_FEED_RANGE: f32 = 600.0;
+// a split cell flies about this many radius of the splitting cell
+const SPLIT_REACH: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BotDifficulty {
+    pub reaction_ticks: u32, // ticks between two decisions
+    pub vision: f32,         // multiplies the view of the bot
+    pub split_kill: bool,
+    pub feed_virus: bool,
+}
+
+impl BotDifficulty {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "easy" => BotDifficulty {
+                reaction_ticks: 15,
+                vision: 0.6,
+                split_kill: false,
+                feed_virus: false,
+            },
+            "hard" => BotDifficulty {
+                reaction_ticks: 2,
+                vision: 1.4,
+                split_kill: true,
+                feed_virus: true,
+            },
+            _ => BotDifficulty {
+                reaction_ticks: 6,
+                vision: 1.0,
+                split_kill: true,
+                feed_virus: false,
+            },
+        }
+    }
+}
+
+// a cell of any player, as the bots see it
+#[derive(Debug, Clone, Copy)]
+pub struct OtherCell {
+    pub owner: PlayerID,
+    pub team: Option<u8>,
+    pub position: Point,
+    pub mass: Mass,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BotDecision {
+    pub target: Point,
+    pub split: bool,
+    pub feed: bool,
+}
+
+impl BotDecision {
+    fn go_to(target: Point) -> Self {
+        BotDecision {
+            target,
+            split: false,
+            feed: false,
+        }
+    }
+}
+
+// flee bigger cells first, then feed a virus next to a threat, hunt smaller cells and
+// finally eat the closest food
+pub fn think(
+    bot: &Player,
+    cells: &[OtherCell],
+    view: &VisibleEntities,
+    difficulty: &BotDifficulty,
+    rule: &EatRule,
+    can_split: bool,
+) -> BotDecision {
+    let main_cell = match bot.cells.iter().max_by_key(|cell| cell.mass) {
+        Some(cell) => cell,
+        None => return BotDecision::go_to(bot.get_position_point()),
+    };
+    let position = main_cell.position;
+    let mass = main_cell.mass as f32;
+    let awareness = AWARENESS_RANGE * difficulty.vision;
+
+    let nearby = cells.iter().filter(|cell| {
+        cell.owner != bot.id
+            && (bot.team.is_none() || cell.team != bot.team)
+            && position.distance(&cell.position) < awareness + cell.position.radius
+    });
+
+    let mut threats = vec![];
+    let mut preys = vec![];
+    for cell in nearby {
+        if (cell.mass as f32) > mass * rule.mass_ratio {
+            threats.push(cell);
+        } else if mass > (cell.mass as f32) * rule.mass_ratio {
+            preys.push(cell);
+        }
+    }
+
+    if !threats.is_empty() {
+        if difficulty.feed_virus {
+            let virus = view.visible_viruses.iter().find(|virus| {
+                let virus_position = virus.get_position();
+                threats
+                    .iter()
+                    .any(|threat| threat.position.distance(&virus_position) < VIRUS_FEED_RANGE)
+            });
+
+            if let Some(virus) = virus {
+                return BotDecision {
+                    target: virus.get_position(),
+                    split: false,
+                    feed: true,
+                };
+            }
+        }
+
+        // run away from every threat, the closest ones count more
+        let mut away = Point {
+            x: 0.0,
+            y: 0.0,
+            radius: 0.0,
+        };
+        for threat in threats {
+            let distance = position.distance(&threat.position).max(1.0);
+            away.x += (position.x - threat.position.x) / (distance * distance);
+            away.y += (position.y - threat.position.y) / (distance * distance);
+        }
+        let away = away.normalize().scale(awareness);
+
+        return BotDecision::go_to(Point {
+            x: position.x + away.x,
+            y: position.y + away.y,
+            radius: 0.0,
+        });
+    }
+
+    let prey = preys.into_iter().min_by(|a, b| {
+        position
+            .distance_pow(&a.position)
+            .total_cmp(&position.distance_pow(&b.position))
+    });
+
+    if let Some(prey) = prey {
+        let split = difficulty.split_kill
+            && can_split
+            && mass / 2.0 > (prey.mass as f32) * rule.mass_ratio
+            && position.distance(&prey.position) < position.radius * SPLIT_REACH;
+
+        return BotDecision {
+            target: prey.position,
+            split,
+            feed: false,
+        };
+    }
+
+    let food = view
+        .visible_foods
+        .iter()
+        .map(|food| food.as_point())
+        .chain(view.visible_mass_food.iter().map(|mass_food| mass_food.point))
+        .min_by(|a, b| position.distance_pow(a).total_cmp(&position.distance_pow(b)));
+
+    match food {
+        Some(food) => BotDecision::go_to(food),
+        None => BotDecision::go_to(position),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game::VisibleEntities,
+        map::{food::Food, player::Player, point::Point},
+        utils::{consts::Mass, rules::EatRules},
+    };
+
+    use super::{think, BotDifficulty, OtherCell};
+
+    fn point(x: f32, y: f32) -> Point {
+        Point { x, y, radius: 0.0 }
+    }
+
+    fn bot(mass: Mass) -> Player {
+        let mut bot = Player::new(0);
+        bot.reset(&point(5_000.0, 5_000.0), mass);
+        bot
+    }
+
+    fn other(owner: u8, x: f32, y: f32, mass: Mass) -> OtherCell {
+        let mut position = point(x, y);
+        position.radius = crate::utils::util::mass_to_radius(mass);
+        OtherCell {
+            owner,
+            team: None,
+            position,
+            mass,
+        }
+    }
+
+    fn view(foods: Vec<Food>) -> VisibleEntities {
+        VisibleEntities {
+            visible_foods: foods,
+            visible_viruses: vec![],
+            visible_mass_food: vec![],
+        }
+    }
+
+    #[test]
+    fn test_bot_flees_bigger_cells() {
+        let bot = bot(100);
+        let difficulty = BotDifficulty::from_name("normal");
+        let rule = EatRules::default().cell;
+
+        let decision = think(
+            &bot,
+            &[other(1, 5_300.0, 5_000.0, 1_000)],
+            &view(vec![]),
+            &difficulty,
+            &rule,
+            true,
+        );
+
+        assert!(decision.target.x < 5_000.0);
+        assert!(!decision.split);
+    }
+
+    #[test]
+    fn test_bot_hunts_smaller_cells() {
+        let bot = bot(1_000);
+        let difficulty = BotDifficulty::from_name("normal");
+        let rule = EatRules::default().cell;
+        let cells = [other(1, 5_100.0, 5_000.0, 100), other(2, 5_800.0, 5_000.0, 100)];
+
+        let decision = think(&bot, &cells, &view(vec![]), &difficulty, &rule, true);
+        assert_eq!(decision.target, cells[0].position);
+        assert!(decision.split);
+
+        // too far away to split on it
+        let decision = think(&bot, &cells[1..], &view(vec![]), &difficulty, &rule, true);
+        assert_eq!(decision.target, cells[1].position);
+        assert!(!decision.split);
+
+        let easy = BotDifficulty::from_name("easy");
+        let decision = think(&bot, &cells, &view(vec![]), &easy, &rule, true);
+        assert!(!decision.split);
+    }
+
+    #[test]
+    fn test_bot_seeks_closest_food() {
+        let bot = bot(100);
+        let difficulty = BotDifficulty::from_name("normal");
+        let rule = EatRules::default().cell;
+        let foods = vec![
+            Food::new(1, &point(5_200.0, 5_000.0), &mut rand::thread_rng()),
+            Food::new(2, &point(5_050.0, 5_050.0), &mut rand::thread_rng()),
+        ];
+
+        // same size players are ignored
+        let decision = think(
+            &bot,
+            &[other(1, 5_100.0, 5_000.0, 100)],
+            &view(foods),
+            &difficulty,
+            &rule,
+            true,
+        );
+        assert_eq!((decision.target.x, decision.target.y), (5_050.0, 5_050.0));
+    }
+}
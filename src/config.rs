@@ -1,6 +1,16 @@
 use std::{env, sync::OnceLock};
 
-use crate::utils::consts::{Mass, TotalMass};
+use log::error;
+
+use crate::{
+    bot::BotDifficulty,
+    map::{arena::ArenaMap, power_up::PowerUpKind},
+    utils::{
+        consts::{Mass, TotalMass},
+        rules::EatRules,
+        spawn::SpawnStrategy,
+    },
+};
 
 #[derive(Debug)]
 pub struct Config {
@@ -9,14 +19,17 @@ pub struct Config {
     pub logpath: String,
     pub food_mass: Mass,
     pub fire_food: Mass,
+    pub feed_interval: i64, // milliseconds between two ejections while feeding
+    pub spawn_protection: i64, // seconds a respawned player can't eat nor be eaten
     pub limit_split: u32,
     pub split_min_mass: Mass,
     pub default_player_mass: Mass,
+    pub max_cell_mass: Mass, // bigger cells split on their own
     pub virus: VirusConfig, // Ensure VirusConfig is also publicly accessible if needed
     pub game_width: u32,
     pub game_height: u32,
     pub food_capacity_q: u32,
-    pub admin_pass: String,
+    pub admin_pass: Option<String>, // ADMIN_PASS, admin commands are refused without it
     pub game_mass: TotalMass,
     pub max_food: usize,
     pub max_virus: usize,
@@ -24,10 +37,30 @@ pub struct Config {
     pub log_chat: bool,
     pub network_update_factor: u32,
     pub max_heartbeat_interval: i64,
-    pub food_uniform_disposition: bool,
-    pub new_player_initial_position: String,
+    pub spawn: SpawnConfig,
     pub mass_loss_rate: f32,
     pub min_mass_loss: Mass,
+    pub round: RoundConfig,
+    pub drain_cashout_window: u64,
+    pub snapshot: SnapshotConfig,
+    pub eat_rules: EatRules,
+    pub game_mode: String,
+    pub teams: u8, // only used by the teams mode, 2 or 3
+    pub battle_royale: BattleRoyaleConfig,
+    pub bots: BotConfig,
+    pub power_ups: PowerUpConfig,
+    pub teleport: TeleportConfig,
+    pub mass_food: MassFoodConfig,
+    pub arena: ArenaMap, // game_width and game_height come from it
+    pub rng_seed: Option<u64>, // same seed, same food layout
+}
+
+#[derive(Debug)]
+pub struct SpawnConfig {
+    pub strategy: SpawnStrategy,
+    pub food_range: f32,     // half size of the square where the food is counted
+    pub blob_range: f32,     // how close ejected mass has to be to count as one pile
+    pub blob_min_mass: Mass, // mass of a pile needed to spawn on it
 }
 
 impl Config {
@@ -54,6 +87,95 @@ pub struct VirusConfig {
     pub default_mass: DefaultMass,
     pub split_mass: Mass,
     pub uniform_disposition: bool,
+    pub pop: VirusPopConfig,
+    pub feed_threshold: Mass, // a fed virus shoots a new one past this mass
+    pub shoot_speed: f32,
+    pub feed_push: f32, // speed given to a virus by each mass food it eats
+    pub max_count: usize, // hard cap, shot viruses included, max_virus is only the spawn target
+}
+
+// How a cell bursts when it eats a virus, the pieces still respect `limit_split`
+#[derive(Debug)]
+pub struct VirusPopConfig {
+    pub max_pieces: u8, // new cells created by one pop
+    pub min_piece_mass: Mass,
+    pub big_pieces: u8, // the cell itself counts as one of them
+    pub big_share: f32, // part of the spare mass that goes to the big pieces
+}
+
+// Timed rounds are disabled unless ROUND_DURATION (in seconds) is set
+#[derive(Debug)]
+pub struct RoundConfig {
+    pub duration: Option<i64>,
+    pub countdown: i64,
+}
+
+#[derive(Debug)]
+pub struct SnapshotConfig {
+    pub path: String,
+    pub interval: u64,
+    pub reconnect_window: i64,
+}
+
+// Only used by the battle royale mode, times in seconds
+#[derive(Debug)]
+pub struct BattleRoyaleConfig {
+    pub shrink_delay: i64,
+    pub shrink_duration: i64,
+    pub min_zone_size: f32,
+    pub zone_damage_rate: f32, // per mille of the cell mass lost every tick outside the zone
+}
+
+// Bots join while there are less than BOTS_FILL_TO players, unset or 0 disables them
+#[derive(Debug)]
+pub struct BotConfig {
+    pub fill_to: usize,
+    pub difficulty: BotDifficulty,
+}
+
+// Ejected mass shrinks after decay_start until it vanishes at lifetime (in seconds), past
+// max_count the oldest blobs are removed
+#[derive(Debug)]
+pub struct MassFoodConfig {
+    pub lifetime: i64,
+    pub decay_start: i64,
+    pub max_count: usize,
+    pub separation: f32, // part of the overlap two resting blobs push away each tick
+}
+
+// a player holds up to max_charges, one charge comes back every recharge seconds.
+// cooldown is the minimum time between two teleports, in seconds
+#[derive(Debug)]
+pub struct TeleportConfig {
+    pub max_charges: u8,
+    pub recharge: i64,
+    pub cooldown: i64,
+    pub min_mass: TotalMass,
+    pub max_mass: TotalMass,
+    pub safe_distance: f32, // room kept between the arrival and any bigger cell
+}
+
+// durations in seconds
+#[derive(Debug)]
+pub struct PowerUpConfig {
+    pub max: usize,
+    pub speed_duration: i64,
+    pub speed_multiplier: f32,
+    pub merge_reset_duration: i64,
+    pub shield_duration: i64,
+    pub magnet_duration: i64,
+    pub magnet_range: f32,
+}
+
+impl PowerUpConfig {
+    pub fn get_duration(&self, kind: PowerUpKind) -> i64 {
+        match kind {
+            PowerUpKind::Speed => self.speed_duration,
+            PowerUpKind::MergeReset => self.merge_reset_duration,
+            PowerUpKind::Shield => self.shield_duration,
+            PowerUpKind::Magnet => self.magnet_duration,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -62,8 +184,28 @@ pub struct DefaultMass {
     pub to: Mass,
 }
 
+// MAP_FILE points to a json map definition, without it the arena is a plain 15000 x 15000 square.
+// WRAP_AROUND=true makes any arena toroidal
+fn load_arena() -> ArenaMap {
+    let mut arena = match env::var("MAP_FILE") {
+        Ok(path) => ArenaMap::load(&path).unwrap_or_else(|err| {
+            error!("Could not load the map [{}]: {:?}", path, err);
+            ArenaMap::plain(15000, 15000)
+        }),
+        Err(_) => ArenaMap::plain(15000, 15000),
+    };
+
+    arena.wrap |= env::var("WRAP_AROUND")
+        .ok()
+        .and_then(|w| w.parse().ok())
+        .unwrap_or(false);
+    arena
+}
+
 impl Default for Config {
     fn default() -> Self {
+        let arena = load_arena();
+
         Config {
             host: "0.0.0.0".to_string(),
             port: env::var("PORT")
@@ -73,14 +215,17 @@ impl Default for Config {
             logpath: "logger.php".to_string(),
             food_mass: 1,
             fire_food: 20,
+            feed_interval: 100,
+            spawn_protection: 3,
             limit_split: 16,
             split_min_mass: 17,
             default_player_mass: 10,
+            max_cell_mass: 22_500,
             virus: VirusConfig::default(),
-            game_width: 15000,
-            game_height: 15000,
+            game_width: arena.width,
+            game_height: arena.height,
             food_capacity_q: 100,
-            admin_pass: "DEFAULT".to_string(),
+            admin_pass: env::var("ADMIN_PASS").ok().filter(|pass| !pass.is_empty()),
             game_mass: 500000,
             max_food: 4_000,
             max_virus: 100,
@@ -88,10 +233,35 @@ impl Default for Config {
             log_chat: false,
             network_update_factor: 30,
             max_heartbeat_interval: 50000,
-            food_uniform_disposition: true,
-            new_player_initial_position: "farthest".to_string(),
+            spawn: SpawnConfig::default(),
             mass_loss_rate: 1.0,
             min_mass_loss: 50,
+            round: RoundConfig::default(),
+            drain_cashout_window: 15,
+            snapshot: SnapshotConfig::default(),
+            eat_rules: EatRules::default(),
+            game_mode: env::var("GAME_MODE").unwrap_or("classic".to_string()),
+            teams: 2,
+            battle_royale: BattleRoyaleConfig::default(),
+            bots: BotConfig::default(),
+            power_ups: PowerUpConfig::default(),
+            teleport: TeleportConfig::default(),
+            mass_food: MassFoodConfig::default(),
+            arena,
+            rng_seed: env::var("RNG_SEED").ok().and_then(|s| s.parse().ok()),
+        }
+    }
+}
+
+impl Default for SpawnConfig {
+    fn default() -> Self {
+        SpawnConfig {
+            strategy: SpawnStrategy::from_name(
+                &env::var("SPAWN_STRATEGY").unwrap_or("farthest".to_string()),
+            ),
+            food_range: 500.0,
+            blob_range: 150.0,
+            blob_min_mass: 100,
         }
     }
 }
@@ -108,6 +278,107 @@ impl Default for VirusConfig {
             },
             split_mass: 180,
             uniform_disposition: false,
+            pop: VirusPopConfig::default(),
+            feed_threshold: 320,
+            shoot_speed: 35.0,
+            feed_push: 3.0,
+            max_count: 150,
+        }
+    }
+}
+
+impl Default for VirusPopConfig {
+    fn default() -> Self {
+        VirusPopConfig {
+            max_pieces: 15,
+            min_piece_mass: 10,
+            big_pieces: 3,
+            big_share: 0.6,
+        }
+    }
+}
+
+impl Default for RoundConfig {
+    fn default() -> Self {
+        RoundConfig {
+            duration: env::var("ROUND_DURATION")
+                .ok()
+                .and_then(|d| d.parse().ok())
+                .filter(|d| *d > 0),
+            countdown: 10,
+        }
+    }
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        SnapshotConfig {
+            path: env::var("SNAPSHOT_PATH").unwrap_or("snapshots/world.json".to_string()),
+            interval: 30,
+            reconnect_window: 120,
+        }
+    }
+}
+
+impl Default for BattleRoyaleConfig {
+    fn default() -> Self {
+        BattleRoyaleConfig {
+            shrink_delay: 60,
+            shrink_duration: 300,
+            min_zone_size: 1000.0,
+            zone_damage_rate: 10.0,
+        }
+    }
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        BotConfig {
+            fill_to: env::var("BOTS_FILL_TO")
+                .ok()
+                .and_then(|b| b.parse().ok())
+                .unwrap_or(0),
+            difficulty: BotDifficulty::from_name(
+                &env::var("BOTS_DIFFICULTY").unwrap_or("normal".to_string()),
+            ),
+        }
+    }
+}
+
+impl Default for TeleportConfig {
+    fn default() -> Self {
+        TeleportConfig {
+            max_charges: 1,
+            recharge: 60,
+            cooldown: 5,
+            min_mass: 0,
+            max_mass: 150,
+            safe_distance: 300.0,
+        }
+    }
+}
+
+impl Default for PowerUpConfig {
+    fn default() -> Self {
+        PowerUpConfig {
+            max: 10,
+            speed_duration: 8,
+            speed_multiplier: 1.5,
+            merge_reset_duration: 5,
+            shield_duration: 8,
+            magnet_duration: 10,
+            magnet_range: 300.0,
+        }
+    }
+}
+
+impl Default for MassFoodConfig {
+    fn default() -> Self {
+        MassFoodConfig {
+            lifetime: 60,
+            decay_start: 30,
+            max_count: 500,
+            separation: 0.5,
         }
     }
 }
@@ -1,40 +1,52 @@
 use std::{
     collections::{HashMap, HashSet, VecDeque},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, Ordering},
+        Arc,
+    },
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
+    bot::{think, OtherCell},
     config::{get_current_config, Config},
     get_server_port,
     managers::{
         amount_manager::AmountManager, food_manager::FoodManager,
         mass_food_manager::MassFoodManager, player_manager::PlayerManager,
-        virus_manager::VirusManager,
+        power_up_manager::PowerUpManager,
+        round_manager::{RoundEvent, RoundManager}, virus_manager::VirusManager,
     },
     map::{
-        food::Food,
-        mass_food::MassFood,
+        food::{Food, FoodData},
+        mass_food::{MassFood, MassFoodInitData},
         player::{Player, PlayerUpdateData},
         point::{AsPoint, Point},
+        power_up::PowerUpKind,
         virus::{Virus, VirusData},
     },
+    modes::{create_game_mode, GameMode},
     player_connection::PlayerConnection,
     recv_messages::AnyEventPacket,
+    snapshot::{PlayerSnapshot, SettlementLog, WorldSnapshot},
     send_messages::{
         AllInitData, FoodAddedMessage, GameUpdateData, KickMessage, KickedMessage, KillMessage,
-        LeaderboardMessage, PlayerRespawnedMessage, RespawnedMessage, SendEvent, TransferInfo,
+        PlayerJoinMessage, PlayerTeleportedMessage, PowerUpPickedMessage, PowerUpsAddedMessage, PlayerRespawnedMessage, RespawnedMessage, RoundCountdownMessage,
+        RoundEndedMessage, RoundStartedMessage, RoundWonMessage, SendEvent, ServerClosingMessage, SpectatingMessage, TransferInfo,
         VirusAddedMessage,
     },
     utils::{
         amount_queue::AmountQueue,
-        consts::{Mass, TotalMass},
+        collision::find_who_ate_who,
+        consts::{Mass, TotalMass, COLLISION_NODE_CAPACITY},
+        game_logic::wrapped_delta,
         id::{FoodID, MassFoodID, PlayerID, VirusID},
-        quad_tree::{QuadTree, Rectangle},
+        quad_tree::{Indexable, QuadTree, Rectangle},
         queue_message::QueueMessage,
+        spawn::{SpawnContext, SpawnStrategy},
         util::{
-            are_colliding, check_who_ate_who, get_current_timestamp, is_visible_entity,
-            random_in_range, uniform_position,
+            are_colliding, get_current_timestamp, get_current_timestamp_micros, mass_to_radius,
+            random_in_range,
         },
     },
 };
@@ -53,8 +65,29 @@ pub struct VisibleEntities {
     pub visible_mass_food: Vec<MassFood>,
 }
 
+// every entity index covers the whole map
+fn create_quad_tree<T: Indexable>(config: &Config) -> QuadTree<T> {
+    QuadTree::new(
+        Rectangle::new(
+            0.0,
+            0.0,
+            config.game_width as f32,
+            config.game_height as f32,
+        ),
+        config.food_capacity_q as usize,
+    )
+    .wrapping(config.arena.wrap)
+}
+
 const GAME_LOOP_INTERVAL: i64 = 1;
 const TICKER_LOOP_FPS: f64 = 1.0 / (30.0 * 1.0);
+// random spots ranked by the food around them for the food rich spawn strategy
+const SPAWN_FOOD_SPOTS: usize = 10;
+// spawn points tried before a teleport is given up
+const TELEPORT_ATTEMPTS: usize = 10;
+// a respawn without a safe spot looks again a few times, SPAWN_RETRY_DELAY ms apart
+const SPAWN_RETRIES: usize = 5;
+const SPAWN_RETRY_DELAY: u64 = 200;
 
 pub struct Game {
     pub port: u16,
@@ -62,6 +95,7 @@ pub struct Game {
     pub food_manager: FoodManager,
     pub virus_manager: RwLock<VirusManager>,
     pub mass_food_manager: RwLock<MassFoodManager>,
+    pub power_up_manager: RwLock<PowerUpManager>,
     pub player_manager: RwLock<PlayerManager>,
     pub main_room: String,
     pub matchmaking_socket: Option<Client>,
@@ -69,6 +103,13 @@ pub struct Game {
     pub amount_queue: Arc<Mutex<VecDeque<AmountQueue>>>,
     pub connections: RwLock<HashMap<PlayerID, Arc<PlayerConnection>>>,
     pub game_start: u64,
+    pub round_manager: Mutex<RoundManager>,
+    draining: AtomicBool,
+    // players loaded from a snapshot, waiting to reconnect (by matchmaking user id)
+    pub restored_players: Mutex<HashMap<i64, PlayerSnapshot>>,
+    restore_deadline: AtomicI64,
+    settlements: Mutex<SettlementLog>,
+    pub mode: Box<dyn GameMode>,
 }
 
 impl Game {
@@ -83,24 +124,18 @@ impl Game {
 
         // Get the Unix timestamp in seconds
         let unix_timestamp = duration_since_epoch.as_secs();
+
+        let mode = create_game_mode(&config.game_mode);
+        info!("Game mode: {}", mode.name());
+
         Game {
             amount_manager: Arc::new(Mutex::new(AmountManager::new())),
             port: *get_server_port(),
-            food_manager: FoodManager::new(
-                config.food_mass,
-                QuadTree::new(
-                    Rectangle::new(
-                        0.0,
-                        0.0,
-                        config.game_width as f32,
-                        config.game_height as f32,
-                    ),
-                    config.food_capacity_q as usize,
-                ),
-            ),
-            virus_manager: RwLock::new(VirusManager::new()),
+            food_manager: FoodManager::new(config.food_mass, create_quad_tree(config)),
+            virus_manager: RwLock::new(VirusManager::new(create_quad_tree(config))),
             update_queue: Mutex::new(VecDeque::new()),
-            mass_food_manager: RwLock::new(MassFoodManager::new()),
+            mass_food_manager: RwLock::new(MassFoodManager::new(create_quad_tree(config))),
+            power_up_manager: RwLock::new(PowerUpManager::new()),
             player_manager: RwLock::new(PlayerManager::new()),
             main_room: "main".to_string(),
             // io_socket,
@@ -108,10 +143,25 @@ impl Game {
             amount_queue: amount_queue,
             connections: RwLock::new(HashMap::new()),
             game_start: unix_timestamp,
+            round_manager: Mutex::new(RoundManager::new(
+                config.round.duration,
+                config.round.countdown,
+                get_current_timestamp(),
+            )),
+            draining: AtomicBool::new(false),
+            restored_players: Mutex::new(HashMap::new()),
+            restore_deadline: AtomicI64::new(0),
+            settlements: Mutex::new(SettlementLog::new(&config.snapshot.path)),
+            mode,
         }
     }
 
     async fn can_cashout(&self, cashout_request_timestamp: u64) -> bool {
+        // Everyone can cashout while the server is closing
+        if self.is_draining() {
+            return true;
+        }
+
         // Unlock and access the game start timestamp
 
         // Calculate the elapsed time
@@ -174,50 +224,96 @@ impl Game {
         info!("Emitting kick for cashout[{}]", cashout_id);
 
         // Emitting to matchmaking for money transfer
-        if let Some(ref match_making_socket) = self.matchmaking_socket {
-            let transfer_info = TransferInfo {
-                id: cashout_id,
-                amount: amount_to_send,
-                port: self.port,
-            };
+        if !self.transfer_balance(cashout_id, amount_to_send).await {
+            return;
+        }
 
-            match match_making_socket
-                .emit(SendEvent::TransferSol, transfer_info)
-                .await
-            {
-                Ok(_) => {
-                    let player_name = {
-                        // Clear player data
-                        let mut mut_player = player.write().await;
-                        mut_player.bet = 0;
-                        mut_player.total_won = 0;
-
-                        mut_player.name.clone()
-                    };
-
-                    // Kick player and notify them
-                    self.kick_player(player_name, player_id).await;
-
-                    //Remove player from game
-                    //self.remove_player(&player_id);
-
-                    //Send Kick player from game
-                    match self.get_player_stream(player_id).await {
-                        Some(cash_out_player_connection) => {
-                            let _ = cash_out_player_connection.emit_bi(SendEvent::RIP, ()).await;
-                        }
-                        None => {
-                            return;
-                        }
-                    };
-                }
-                Err(e) => {
-                    error!("Failed to send TransferSol event: {:?}", e);
-                }
+        let player_name = {
+            // Clear player data
+            let mut mut_player = player.write().await;
+            mut_player.bet = 0;
+            mut_player.total_won = 0;
+
+            mut_player.name.clone()
+        };
+
+        // Kick player and notify them
+        self.kick_player(player_name, player_id).await;
+
+        //Remove player from game
+        //self.remove_player(&player_id);
+
+        //Send Kick player from game
+        if let Some(cash_out_player_connection) = self.get_player_stream(player_id).await {
+            let _ = cash_out_player_connection.emit_bi(SendEvent::RIP, ()).await;
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    // stops new players from joining, gives everyone a last cashout window and then
+    // settles whatever is still at stake, returns true once every player is settled.
+    // returns false if a drain was already running
+    pub async fn drain(&self) -> bool {
+        if self.draining.swap(true, Ordering::Relaxed) {
+            return false;
+        }
+
+        let cashout_window = get_current_config().drain_cashout_window;
+        info!("Draining server, cashout window of {}s", cashout_window);
+
+        self.emit_bi_broadcast(
+            SendEvent::ServerClosing,
+            ServerClosingMessage { cashout_window },
+        )
+        .await;
+
+        let _ = sleep(Duration::from_secs(cashout_window)).await;
+
+        self.settle_all().await
+    }
+
+    // settles every player still holding a bet or winnings. the world snapshot is only
+    // removed once nothing is left at stake, otherwise it is saved so a restart can pay it
+    pub async fn settle_all(&self) -> bool {
+        let players: Vec<_> = self
+            .player_manager
+            .read()
+            .await
+            .players
+            .values()
+            .cloned()
+            .collect();
+
+        let mut all_settled = true;
+        for player in players {
+            let mut player = player.write().await;
+            if !self.settle_player(&mut player).await {
+                error!(
+                    "Player[{}] could not be settled before closing, bet = {} / won = {}",
+                    player.id, player.bet, player.total_won
+                );
+                all_settled = false;
             }
-        } else {
-            error!("No matchmaking socket available");
         }
+
+        if !all_settled {
+            self.save_snapshot().await;
+            return false;
+        }
+
+        // everything was settled, a restart must not pay it again
+        if let Err(err) = WorldSnapshot::delete(&get_current_config().snapshot.path).await {
+            info!("No world snapshot removed: {:?}", err);
+        }
+        if let Err(err) = self.settlements.lock().await.clear().await {
+            info!("No settlements removed: {:?}", err);
+        }
+
+        info!("Server drained");
+        true
     }
 
     async fn get_player_stream(&self, player_id: PlayerID) -> Option<Arc<PlayerConnection>> {
@@ -272,9 +368,11 @@ impl Game {
             .player_manager
             .write()
             .await
-            .insert_with_new_id(player)
+            .insert_with_new_id(player.clone())
             .await;
 
+        self.mode.on_join(&mut *player.write().await);
+
         self.connections
             .write()
             .await
@@ -288,13 +386,39 @@ impl Game {
         for player_id in players {
             player_manager.remove_player_by_id(player_id);
             self.remove_player_stream(*player_id).await;
+            self.mode.on_leave(*player_id);
         }
         drop(player_manager);
     }
 
     pub async fn respawn_player(&self, player: Arc<RwLock<Player>>) {
+        // a restored player comes back where it was, the others need a safe spot first
+        let restored = player.read().await.restored;
+        let spawn_point = if restored {
+            None
+        } else {
+            match self.find_spawn_point(&player).await {
+                Some(spawn_point) => Some(spawn_point),
+                None => {
+                    let player_id = player.read().await.id;
+                    info!("No safe spawn point for player[{}]", player_id);
+                    self.notify_spawn_failed(player_id).await;
+                    return;
+                }
+            }
+        };
+
+        {
+            let player = player.read().await;
+            if !restored && !self.mode.on_spawn(&player) {
+                self.notify_spectating(player.id).await;
+                return;
+            }
+        }
+
         // check if player is at the game...
-        self.player_manager
+        let rejoined = self
+            .player_manager
             .write()
             .await
             .insert_if_not_in(player.clone())
@@ -314,16 +438,23 @@ impl Game {
             .read()
             .await
             .get_mass_food_init_data();
+        let power_ups_init_data = self.power_up_manager.read().await.data.clone();
 
-        let points = self
-            .player_manager
-            .read()
-            .await
-            .collect_and_clone_all_pos()
-            .await;
-        let spawn_point = self.create_player_spawn_point(points);
         let mut player = player.write().await;
-        player.reset(&spawn_point, get_current_config().default_player_mass);
+        let spawn_point = match spawn_point {
+            None => {
+                player.restored = false;
+                player.get_position_point()
+            }
+            Some(spawn_point) => {
+                // the mode let go of the player when it died, join it again before spawning
+                if rejoined {
+                    self.mode.on_join(&mut player);
+                }
+                player.reset(&spawn_point, get_current_config().default_player_mass);
+                spawn_point
+            }
+        };
 
         // send init data
         if let Some(player_connection) = self.get_player_stream(player.id).await {
@@ -335,6 +466,7 @@ impl Game {
                         virus: virus_init_data,
                         mass_foods: mass_food_init_data,
                         foods: foods_init_data,
+                        power_ups: power_ups_init_data,
                     },
                 )
                 .await;
@@ -355,6 +487,31 @@ impl Game {
         // );
     }
 
+    // the player stays dead, the client can ask for a respawn again
+    async fn notify_spawn_failed(&self, player_id: PlayerID) {
+        if let Some(player_connection) = self.get_player_stream(player_id).await {
+            let _ = player_connection.emit_bi(SendEvent::SpawnFailed, ()).await;
+        }
+    }
+
+    async fn notify_spectating(&self, player_id: PlayerID) {
+        let Some(player_connection) = self.get_player_stream(player_id).await else {
+            return;
+        };
+
+        let spectating = {
+            let round_manager = self.round_manager.lock().await;
+            SpectatingMessage {
+                round: round_manager.round,
+                round_end: round_manager.get_round_end(),
+            }
+        };
+
+        let _ = player_connection
+            .emit_bi(SendEvent::Spectating, spectating)
+            .await;
+    }
+
     async fn kick_player(&self, player_name: Option<String>, player_id: PlayerID) {
         info!("Kicking player {} - {:?}", player_id, player_name);
 
@@ -384,6 +541,352 @@ impl Game {
         }
 
         self.remove_player_stream(player_id).await;
+        self.mode.on_leave(player_id);
+    }
+
+    async fn transfer_balance(&self, user_id: i64, amount: u64) -> bool {
+        if let Some(ref match_making_socket) = self.matchmaking_socket {
+            let transfer_info = TransferInfo {
+                id: user_id,
+                amount,
+                port: self.port,
+            };
+
+            match match_making_socket
+                .emit(SendEvent::TransferSol, transfer_info)
+                .await
+            {
+                Ok(_) => {
+                    self.record_settlement(user_id).await;
+                    true
+                }
+                Err(e) => {
+                    error!("Failed to send TransferSol event: {:?}", e);
+                    false
+                }
+            }
+        } else {
+            error!("No matchmaking socket available");
+            false
+        }
+    }
+
+    // written right away, a snapshot older than this must not give the balance back
+    async fn record_settlement(&self, user_id: i64) {
+        let settlements = self.settlements.lock().await;
+        if let Err(err) = settlements.record(user_id, get_current_timestamp()).await {
+            error!("Error recording settlement of user[{}]: {:?}", user_id, err);
+        }
+    }
+
+    // sends everything the player has at stake back through the matchmaking service
+    pub async fn settle_player(&self, player: &mut Player) -> bool {
+        let amount_to_send = player.bet + player.total_won;
+
+        if amount_to_send == 0 {
+            return true;
+        }
+
+        let user_id = self
+            .amount_manager
+            .lock()
+            .await
+            .get_user_id(player.id)
+            .unwrap_or_default();
+
+        info!(
+            "Settling player[{}] / amount = {}",
+            player.id, amount_to_send
+        );
+
+        if self.transfer_balance(user_id, amount_to_send).await {
+            player.bet = 0;
+            player.total_won = 0;
+            return true;
+        }
+
+        false
+    }
+
+    pub async fn save_snapshot(&self) -> bool {
+        // taken first, a settlement made while the players are read is newer and kept
+        let timestamp = get_current_timestamp();
+        let live_players: Vec<_> = self
+            .player_manager
+            .read()
+            .await
+            .players
+            .values()
+            .cloned()
+            .collect();
+
+        let mut players = vec![];
+        for player in live_players {
+            let player = player.read().await;
+            let user_id = self.amount_manager.lock().await.get_user_id(player.id);
+
+            // without a matchmaking id the player can't be matched on reconnect
+            if let Some(user_id) = user_id {
+                players.push(player.generate_snapshot(user_id));
+            }
+        }
+        players.extend(self.restored_players.lock().await.values().cloned());
+
+        let snapshot = WorldSnapshot {
+            timestamp,
+            foods: self.food_manager.get_all_foods().await,
+            viruses: self.virus_manager.read().await.data.clone(),
+            mass_foods: self.mass_food_manager.read().await.data.clone(),
+            players,
+        };
+
+        match snapshot.save(&get_current_config().snapshot.path).await {
+            Ok(_) => {
+                debug!("World snapshot saved");
+
+                let settlements = self.settlements.lock().await;
+                if let Err(err) = settlements.prune(timestamp).await {
+                    error!("Error pruning settlements: {:?}", err);
+                }
+                true
+            }
+            Err(err) => {
+                error!("Error saving world snapshot: {:?}", err);
+                false
+            }
+        }
+    }
+
+    pub async fn restore_snapshot(&self) {
+        let config = get_current_config();
+
+        let snapshot = match WorldSnapshot::load(&config.snapshot.path).await {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                info!("No world snapshot restored: {:?}", err);
+                return;
+            }
+        };
+
+        info!(
+            "Restoring world snapshot from {} - {} players",
+            snapshot.timestamp,
+            snapshot.players.len()
+        );
+
+        self.food_manager.restore(snapshot.foods).await;
+        self.virus_manager.write().await.restore(snapshot.viruses);
+        self.mass_food_manager
+            .write()
+            .await
+            .restore(snapshot.mass_foods);
+
+        // paid out or killed after the snapshot was taken, their balance is gone
+        let settled = self
+            .settlements
+            .lock()
+            .await
+            .settled_since(snapshot.timestamp)
+            .await;
+
+        let mut restored_players = self.restored_players.lock().await;
+        for player in snapshot.players {
+            if settled.contains(&player.user_id) {
+                info!("Player[{}] was settled after the snapshot", player.user_id);
+                continue;
+            }
+            restored_players.insert(player.user_id, player);
+        }
+
+        self.restore_deadline.store(
+            get_current_timestamp() + config.snapshot.reconnect_window,
+            Ordering::Relaxed,
+        );
+    }
+
+    // gives back the cells and balances the player had before the restart, if any.
+    // the user id must come from the matchmaking server, never from the client
+    async fn restore_player(
+        &self,
+        players_manager: &PlayerManager,
+        id: PlayerID,
+        user_id: i64,
+    ) -> bool {
+        let player = match players_manager.players.get(&id) {
+            Some(player) => player,
+            None => return false,
+        };
+        let snapshot = match self.restored_players.lock().await.remove(&user_id) {
+            Some(snapshot) => snapshot,
+            None => return false,
+        };
+
+        player.write().await.restore_snapshot(snapshot);
+
+        info!("Player[{}] restored from snapshot", id);
+        true
+    }
+
+    // settles the players that did not come back within the reconnect window
+    async fn expire_restored_players(&self) {
+        if get_current_timestamp() < self.restore_deadline.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut restored_players = self.restored_players.lock().await;
+        if restored_players.is_empty() {
+            return;
+        }
+
+        let expired: Vec<_> = restored_players.drain().map(|(_, p)| p).collect();
+        drop(restored_players);
+
+        for player in expired {
+            let amount = player.bet + player.total_won;
+            if amount == 0 {
+                continue;
+            }
+
+            info!(
+                "Restored player[{}] did not reconnect, settling {}",
+                player.user_id, amount
+            );
+
+            if !self.transfer_balance(player.user_id, amount).await {
+                // keep it so the next snapshot still holds the balance
+                self.restored_players
+                    .lock()
+                    .await
+                    .insert(player.user_id, player);
+            }
+        }
+    }
+
+    pub async fn snapshot_loop(&self) {
+        let interval = Duration::from_secs(get_current_config().snapshot.interval);
+
+        loop {
+            let _ = sleep(interval).await;
+
+            if self.is_draining() {
+                break;
+            }
+
+            self.save_snapshot().await;
+        }
+    }
+
+    pub async fn handle_round(&self, players_manager: &PlayerManager) {
+        let (round, round_event) = {
+            let round_manager = self.round_manager.lock().await;
+            (
+                round_manager.round,
+                round_manager.check(get_current_timestamp()),
+            )
+        };
+
+        match round_event {
+            Some(RoundEvent::Countdown(seconds_left)) => {
+                self.emit_bi_broadcast(
+                    SendEvent::RoundCountdown,
+                    RoundCountdownMessage {
+                        round,
+                        seconds_left,
+                    },
+                )
+                .await;
+            }
+            Some(RoundEvent::Ended) => {
+                self.end_round(players_manager, round).await;
+            }
+            None => {}
+        }
+    }
+
+    async fn end_round(&self, players_manager: &PlayerManager, round: u32) {
+        info!("Round {} ended", round);
+
+        let standings = players_manager.get_standings().await;
+        self.emit_bi_broadcast(SendEvent::RoundEnded, RoundEndedMessage { round, standings })
+            .await;
+
+        for player in players_manager.players.values() {
+            let mut player = player.write().await;
+            self.settle_player(&mut player).await;
+        }
+
+        // the mode starts the round first, the players are spawned into it
+        let round_started = {
+            let mut round_manager = self.round_manager.lock().await;
+            round_manager.start_next(get_current_timestamp());
+            self.mode.on_round_start(round_manager.round_start);
+
+            RoundStartedMessage {
+                round: round_manager.round,
+                start: round_manager.round_start,
+                end: round_manager.get_round_end().unwrap_or_default(),
+            }
+        };
+
+        self.reset_world(players_manager).await;
+
+        info!("Round {} started", round_started.round);
+
+        self.emit_bi_broadcast(SendEvent::RoundStarted, round_started)
+            .await;
+    }
+
+    // wipes food, viruses and mass food and puts every player back on a fresh spawn point
+    async fn reset_world(&self, players_manager: &PlayerManager) {
+        let config = get_current_config();
+
+        self.food_manager.clear().await;
+        self.virus_manager.write().await.clear();
+        self.mass_food_manager.write().await.clear();
+        self.power_up_manager.write().await.clear();
+
+        // the world is empty, only the players placed so far are in the way
+        let mut spawn_context = SpawnContext::default();
+        for player in players_manager.players.values() {
+            let mut player = player.write().await;
+            let Some(spawn_point) = self.create_player_spawn_point(&player, &spawn_context) else {
+                // out of the new round until a respawn finds some room
+                player.cells.clear();
+                self.notify_spawn_failed(player.id).await;
+                continue;
+            };
+
+            if !self.mode.on_spawn(&player) {
+                player.cells.clear();
+                self.notify_spectating(player.id).await;
+                continue;
+            }
+
+            player.reset(&spawn_point, config.default_player_mass);
+            player.effects.clear();
+            spawn_context.cells.push(spawn_point);
+
+            if let Some(player_connection) = self.get_player_stream(player.id).await {
+                let _ = player_connection
+                    .emit_bi(SendEvent::Respawned, RespawnedMessage(spawn_point))
+                    .await;
+            }
+        }
+
+        self.emit_bi_broadcast(
+            SendEvent::AllInitData,
+            AllInitData {
+                players: players_manager.get_players_init_data().await,
+                virus: vec![],
+                mass_foods: vec![],
+                foods: vec![],
+                power_ups: vec![],
+            },
+        )
+        .await;
+
+        self.balance_mass(config.game_mass, config.max_food, config.max_virus)
+            .await;
     }
 
     pub async fn tick_player(
@@ -391,7 +894,9 @@ impl Game {
         player: &mut Player,
         config: &Config,
     ) -> Option<(HashSet<FoodID>, HashSet<MassFoodID>, HashSet<VirusID>)> {
-        if player.last_heartbeat < (get_current_timestamp() - config.max_heartbeat_interval) {
+        if !player.bot
+            && player.last_heartbeat < (get_current_timestamp() - config.max_heartbeat_interval)
+        {
             self.update_queue
                 .lock()
                 .await
@@ -402,6 +907,9 @@ impl Game {
             return None;
         }
 
+        player.update_effects(get_current_timestamp());
+        player.update_teleport_charges(get_current_timestamp());
+
         player.move_cells(
             config.slow_base as f32,
             config.game_width as i32,
@@ -409,6 +917,8 @@ impl Game {
             config.get_init_mass_log(),
         );
 
+        self.mode.update_player(player);
+
         let player_view = self.enumerate_what_player_sees(player).await;
 
         let mut cells_to_split: Vec<usize> = vec![];
@@ -417,11 +927,21 @@ impl Game {
         let mut eated_mass: HashSet<MassFoodID> = HashSet::new();
         let mut eated_virus: HashSet<VirusID> = HashSet::new();
 
+        // the magnet widens the reach of the cells on foods
+        let magnet_range = if player.has_effect(PowerUpKind::Magnet) {
+            config.power_ups.magnet_range
+        } else {
+            0.0
+        };
+
         for (i, p_cell) in player.cells.iter_mut().enumerate() {
             let mut mass_gained: Mass = 0;
 
+            let mut reach = p_cell.position;
+            reach.radius += magnet_range;
+
             for food in player_view.visible_foods.iter() {
-                if are_colliding(&p_cell.position, &food.as_point()) {
+                if are_colliding(&reach, &food.as_point()) {
                     if eated_foods.insert(food.id, &food).is_none() {
                         mass_gained = mass_gained.saturating_add(1);
                     }
@@ -429,7 +949,7 @@ impl Game {
             }
 
             for mass in player_view.visible_mass_food.iter() {
-                if mass.can_be_eat_by(p_cell.mass, &p_cell.position) {
+                if mass.can_be_eat_by(&config.eat_rules.mass_food, p_cell.mass, &p_cell.position) {
                     if eated_mass.insert(mass.id) {
                         mass_gained = mass_gained.saturating_add(mass.mass);
                     }
@@ -437,7 +957,7 @@ impl Game {
             }
 
             for virus in player_view.visible_viruses.iter() {
-                if virus.can_be_eat_by(p_cell.mass, &p_cell.position) {
+                if virus.can_be_eat_by(&config.eat_rules.virus, p_cell.mass, &p_cell.position) {
                     if eated_virus.insert(virus.id) {
                         mass_gained = mass_gained.saturating_add(virus.mass);
                         cells_to_split.push(i);
@@ -487,25 +1007,158 @@ impl Game {
             player.virus_split(
                 &cells_to_split,
                 config.limit_split as usize,
-                config.default_player_mass,
+                &config.virus.pop,
             );
         }
 
+        self.pick_power_ups(player, config).await;
+
+        player.auto_split(config.limit_split as usize, config.max_cell_mass);
         player.recalculate_total_mass();
         player.recalculate_ratio();
 
         Some((eated_foods_id, eated_mass, eated_virus))
     }
 
-    pub fn create_player_spawn_point(&self, points: Vec<Point>) -> Point {
-        let config = get_current_config();
-        uniform_position(&points, config.default_player_mass as f32)
-    }
-
-    // returns the shoot direction if the virus "exploded"
+    async fn pick_power_ups(&self, player: &mut Player, config: &Config) {
+        let picked = self.power_up_manager.write().await.take_touched(&player.cells);
+        let now = get_current_timestamp();
+
+        for power_up in picked {
+            let until = now + config.power_ups.get_duration(power_up.kind);
+            player.add_effect(power_up.kind, until);
+
+            self.emit_bi_broadcast(
+                SendEvent::PowerUpPicked,
+                PowerUpPickedMessage {
+                    id: power_up.id,
+                    player_id: player.id,
+                    kind: power_up.kind,
+                    until,
+                },
+            )
+            .await;
+        }
+    }
+
+    pub fn create_player_spawn_point(
+        &self,
+        player: &Player,
+        context: &SpawnContext,
+    ) -> Option<Point> {
+        self.mode.spawn_point(player, context)
+    }
+
+    // the world changes every tick, a spot taken now can be free a moment later
+    async fn find_spawn_point(&self, player: &Arc<RwLock<Player>>) -> Option<Point> {
+        for attempt in 0..SPAWN_RETRIES {
+            if attempt > 0 {
+                let _ = sleep(Duration::from_millis(SPAWN_RETRY_DELAY)).await;
+            }
+
+            let spawn_context = self.spawn_context().await;
+            let spawn_point = self.create_player_spawn_point(&*player.read().await, &spawn_context);
+            if spawn_point.is_some() {
+                return spawn_point;
+            }
+        }
+
+        None
+    }
+
+    // uses a charge to move the player to a spawn point far enough from any bigger cell,
+    // nothing happens when no such point is found
+    pub async fn teleport_player(&self, player: Arc<RwLock<Player>>) {
+        let teleport = &get_current_config().teleport;
+        let spawn_context = self.spawn_context().await;
+        let now = get_current_timestamp();
+
+        let message = {
+            let mut player = player.write().await;
+            if !player.can_teleport(now) {
+                return;
+            }
+
+            let radius = player
+                .cells
+                .iter()
+                .map(|cell| cell.position.radius)
+                .fold(0.0, f32::max);
+            let is_clear = |point: &Point| {
+                spawn_context.cells.iter().all(|cell| {
+                    cell.radius <= radius
+                        || point.distance(cell) - cell.radius - radius >= teleport.safe_distance
+                })
+            };
+
+            let arrival = (0..TELEPORT_ATTEMPTS)
+                .filter_map(|_| self.create_player_spawn_point(&player, &spawn_context))
+                .find(is_clear);
+            let Some(arrival) = arrival else {
+                return;
+            };
+
+            let from = player.get_position_point();
+            if !player.teleport(&arrival, now) {
+                return;
+            }
+
+            PlayerTeleportedMessage {
+                player_id: player.id,
+                from,
+                to: player.get_position_point(),
+                charges: player.teleport_charges,
+            }
+        };
+
+        self.emit_bi_broadcast(SendEvent::PlayerTeleported, message)
+            .await;
+    }
+
+    // only what the configured strategy needs, must be called before locking the player
+    pub async fn spawn_context(&self) -> SpawnContext {
+        let config = get_current_config();
+        let cells = self
+            .player_manager
+            .read()
+            .await
+            .collect_and_clone_all_pos()
+            .await;
+
+        let food_spots = match config.spawn.strategy {
+            SpawnStrategy::FoodRich => {
+                self.food_manager
+                    .richest_spots(
+                        SPAWN_FOOD_SPOTS,
+                        config.spawn.food_range,
+                        mass_to_radius(config.default_player_mass),
+                    )
+                    .await
+            }
+            _ => vec![],
+        };
+
+        let blobs = match config.spawn.strategy {
+            SpawnStrategy::MassBlob => self
+                .mass_food_manager
+                .read()
+                .await
+                .find_blobs(config.spawn.blob_range, config.spawn.blob_min_mass),
+            _ => vec![],
+        };
+
+        SpawnContext {
+            cells,
+            food_spots,
+            blobs,
+        }
+    }
+
+    // returns the shoot direction if the virus "exploded", it only shoots when `can_shoot`
     pub async fn tick_virus(
         &self,
         virus: &mut Virus,
+        can_shoot: bool,
     ) -> (Vec<MassFoodID>, Option<VirusData>, Option<(Point, Point)>) {
         let mut virus_updated: bool = false;
 
@@ -523,28 +1176,40 @@ impl Game {
         let mut mass_food_eated: Vec<MassFoodID> = vec![];
         let mut mass_gained: Mass = 0;
 
-        let mut player_direction: Option<Point> = None;
+        // sum of the directions of the mass food eaten
+        let mut feed_direction = Point {
+            x: 0.0,
+            y: 0.0,
+            radius: 0.0,
+        };
 
         // get mass eated by the virus
         {
             let mass_food_manager = self.mass_food_manager.read().await;
-            for mass_food in mass_food_manager.data.iter() {
+            let near_mass_food = mass_food_manager.retrieve(&Rectangle::around(&virus_point, 0.0));
+            for mass_food in near_mass_food.iter() {
                 if are_colliding(&mass_food.point, &virus_point) {
                     mass_food_eated.push(mass_food.id);
                     mass_gained = mass_gained.saturating_add(mass_food.mass);
                     virus_updated = true;
 
-                    if player_direction.is_none() {
-                        player_direction = Some(mass_food.direction.clone());
-                    }
+                    feed_direction.x += mass_food.direction.x;
+                    feed_direction.y += mass_food.direction.y;
                 }
             }
         }
 
         let mut shoot_direciton = None;
         if mass_food_eated.len() > 0 {
-            // add mass eated
+            let virus_config = &get_current_config().virus;
+            let feed_direction = feed_direction.normalize();
+
+            // add mass eated, the mass food pushes the virus along
             virus.add_mass(mass_gained);
+            virus.push(
+                &feed_direction,
+                virus_config.feed_push * mass_food_eated.len() as f32,
+            );
 
             {
                 let mut mass_food_manager = self.mass_food_manager.write().await;
@@ -555,14 +1220,17 @@ impl Game {
                 }
             }
 
-            // shoot new virus if virus.mass > 320
-            if virus.mass > 320 {
-                let virus_config = &get_current_config().virus;
-                virus.set_mass(random_in_range(
-                    virus_config.default_mass.from..virus_config.default_mass.to,
-                ));
+            // shoot new virus in the feed direction
+            if virus.mass > virus_config.feed_threshold {
+                if can_shoot {
+                    virus.set_mass(random_in_range(
+                        virus_config.default_mass.from..virus_config.default_mass.to,
+                    ));
 
-                shoot_direciton = Some((virus.get_position(), player_direction.unwrap()));
+                    shoot_direciton = Some((virus.get_position(), feed_direction));
+                } else {
+                    virus.set_mass(virus_config.feed_threshold);
+                }
             }
         }
 
@@ -578,13 +1246,24 @@ impl Game {
     }
 
     pub async fn game_loop(&self, config: &Config, players_manager: &PlayerManager) {
+        self.handle_round(players_manager).await;
+        self.expire_restored_players().await;
+
         self.balance_mass(config.game_mass, config.max_food, config.max_virus)
             .await;
 
+        if let Some(winner) = self.mode.winner(&players_manager.get_standings().await) {
+            self.handle_winner(players_manager, winner).await;
+        }
+
+        if let Some(zone) = self.mode.zone() {
+            self.emit_bi_broadcast(SendEvent::ZoneUpdate, zone).await;
+        }
+
         if players_manager.players.len() > 0 {
-            let leaderboard = players_manager.get_top_players().await;
+            let leaderboard = self.mode.leaderboard(players_manager.get_standings().await);
             let _ = self
-                .emit_bi_broadcast(SendEvent::Leaderboard, LeaderboardMessage { leaderboard })
+                .emit_bi_broadcast(SendEvent::Leaderboard, leaderboard)
                 .await;
             players_manager
                 .shrink_cells(
@@ -596,37 +1275,382 @@ impl Game {
         }
     }
 
+    // pays the mode's pot to the winner and ends the round early
+    async fn handle_winner(&self, players_manager: &PlayerManager, winner: PlayerID) {
+        let round = self.round_manager.lock().await.round;
+        let pot = self.mode.take_pot();
+
+        if let Some(player) = players_manager.players.get(&winner) {
+            let mut player = player.write().await;
+            if player.bot {
+                // bots never hold money, the pot waits for the next winner
+                self.mode.collect_stake(pot);
+            } else {
+                player.total_won += pot;
+            }
+        }
+
+        info!("Player[{}] won round {} / pot = {}", winner, round, pot);
+
+        self.emit_bi_broadcast(SendEvent::RoundWon, RoundWonMessage { round, winner, pot })
+            .await;
+        self.end_round(players_manager, round).await;
+    }
+
+    // the player lost its last cell without a killer (e.g. outside the battle royale zone)
+    async fn eliminate_player(&self, player: &mut Player) {
+        info!("Player [{:?}] was eliminated !", player.name);
+        self.mode.on_death(player, None);
+
+        if let Some(player_connection) = self.get_player_stream(player.id).await {
+            let _ = player_connection.emit_bi(SendEvent::RIP, ()).await;
+        }
+
+        self.emit_bi_broadcast(
+            SendEvent::PlayerDied,
+            KillMessage {
+                killed: player.id,
+                eater: player.id,
+            },
+        )
+        .await;
+
+        if self.mode.collect_stake(player.bet) {
+            player.bet = 0;
+        }
+        self.settle_player(player).await;
+    }
+
+    // ejects on a single feed input, or at the feed rate while the feed input is held
+    async fn tick_feeding(&self, player: &mut Player, now: i64) -> Vec<MassFoodInitData> {
+        let held_feed_due =
+            player.feeding && now - player.last_feed >= get_current_config().feed_interval * 1_000;
+
+        if !held_feed_due && !player.feed_requested {
+            return vec![];
+        }
+
+        player.feed_requested = false;
+        player.last_feed = now;
+        self.fire_food(player).await
+    }
+
+    // ejects fire_food mass from every cell big enough, toward the player's target
+    async fn fire_food(&self, player: &mut Player) -> Vec<MassFoodInitData> {
+        let config = get_current_config();
+        let mut mass_foods_init_data = vec![];
+
+        if player.total_mass < config.min_cell_mass() as usize {
+            return mass_foods_init_data;
+        }
+
+        let player_position = player.get_position_point();
+        let player_target = player.get_target_point();
+        let player_hue = player.hue;
+
+        let mut mass_food_manager = self.mass_food_manager.write().await;
+        for cell in player.cells.iter_mut() {
+            if cell.mass >= config.min_cell_mass() {
+                cell.remove_mass(config.fire_food);
+                mass_foods_init_data.push(mass_food_manager.add_new(
+                    &player_position,
+                    &player_target,
+                    &cell.position,
+                    player_hue,
+                    config.fire_food,
+                ));
+            }
+        }
+
+        if !mass_foods_init_data.is_empty() {
+            player.end_protection();
+        }
+
+        mass_foods_init_data
+    }
+
+    // bots are regular players without a connection, they never hold a bet
+    async fn add_bot(&self) {
+        let config = get_current_config();
+
+        let mut bot = Player::new(0);
+        bot.bot = true;
+        bot.screen_width *= config.bots.difficulty.vision;
+        bot.screen_height *= config.bots.difficulty.vision;
+
+        // no safe spot, the next balance tries again
+        let spawn_context = self.spawn_context().await;
+        let Some(spawn_point) = self.create_player_spawn_point(&bot, &spawn_context) else {
+            return;
+        };
+
+        let bot = Arc::new(RwLock::new(bot));
+
+        let bot_id = self
+            .player_manager
+            .write()
+            .await
+            .insert_with_new_id(bot.clone())
+            .await;
+
+        let mut bot = bot.write().await;
+        bot.setup(Some(format!("Bot{}", bot_id)), None);
+        self.mode.on_join(&mut bot);
+        bot.reset(&spawn_point, config.default_player_mass);
+
+        self.emit_bi_broadcast(
+            SendEvent::NotifyPlayerJoined,
+            PlayerJoinMessage(bot.generate_init_player_data()),
+        )
+        .await;
+        self.emit_bi_broadcast(SendEvent::NotifyPlayerRespawn, PlayerRespawnedMessage(bot_id))
+            .await;
+
+        info!("Bot[{}] added", bot_id);
+    }
+
+    // keeps fill_to players in the arena, a bot leaves for every human who joins
+    async fn balance_bots(&self, fill_to: usize) {
+        let (humans, bots) = {
+            let players_manager = self.player_manager.read().await;
+            let mut humans = 0;
+            let mut bots = vec![];
+            for (player_id, player) in players_manager.players.iter() {
+                if player.read().await.bot {
+                    bots.push(*player_id);
+                } else {
+                    humans += 1;
+                }
+            }
+            (humans, bots)
+        };
+
+        let wanted = fill_to.saturating_sub(humans);
+
+        if bots.len() > wanted {
+            let leaving = &bots[wanted..];
+            self.remove_players(leaving.iter()).await;
+
+            for bot_id in leaving {
+                info!("Bot[{}] removed", bot_id);
+                self.emit_bi_broadcast(
+                    SendEvent::KickPlayer,
+                    KickMessage {
+                        id: *bot_id,
+                        name: None,
+                    },
+                )
+                .await;
+            }
+        }
+
+        for _ in bots.len()..wanted {
+            self.add_bot().await;
+        }
+    }
+
+    // every bot decides where to go once per reaction_ticks
+    async fn tick_bots(&self, players_manager: &PlayerManager, tick: u32) {
+        let config = get_current_config();
+        let difficulty = &config.bots.difficulty;
+
+        if !tick.is_multiple_of(difficulty.reaction_ticks.max(1)) {
+            return;
+        }
+
+        // one read lock per player to see where everybody is
+        let mut cells = vec![];
+        let mut bots = vec![];
+        for player in players_manager.players.values() {
+            let player_ref = player.read().await;
+            if player_ref.bot {
+                bots.push(player.clone());
+            }
+            cells.extend(player_ref.cells.iter().map(|cell| OtherCell {
+                owner: player_ref.id,
+                team: player_ref.team,
+                position: cell.position,
+                mass: cell.mass,
+            }));
+        }
+
+        for bot in bots {
+            let mut bot = bot.write().await;
+            let view = self.enumerate_what_player_sees(&bot).await;
+            let can_split = bot.cells.len() < config.limit_split as usize;
+
+            let decision = think(
+                &bot,
+                &cells,
+                &view,
+                difficulty,
+                &config.eat_rules.cell,
+                can_split,
+            );
+
+            // the short way around on a wrapping map
+            let (target_x, target_y) = wrapped_delta(
+                &bot.get_position_point(),
+                &decision.target,
+                config.arena.wrap_size(),
+            );
+            bot.target_x = target_x;
+            bot.target_y = target_y;
+
+            if decision.split {
+                bot.user_split(config.limit_split as usize, config.split_min_mass);
+            }
+
+            if decision.feed {
+                bot.feed_requested = true;
+            }
+        }
+    }
+
     // returns a list of (player_who_eat, player_eated) - (id, cell_index)
     pub async fn get_players_collision(
         players_manager: &PlayerManager,
     ) -> Vec<((PlayerID, usize), (PlayerID, usize))> {
-        let mut who_ate_who_list: Vec<((_, _), (_, _))> = vec![];
-
-        // handling collision btw players
-        let players: Vec<_> = players_manager.players.values().collect();
-        for player_a_index in 0..players.len() {
-            for player_b_index in player_a_index + 1..players.len() {
-                let player_a = players.get(player_a_index).unwrap().read().await;
-                let player_b = players.get(player_b_index).unwrap().read().await;
-
-                for (cell_a_index, cell_a) in player_a.cells.iter().enumerate() {
-                    for (cell_b_index, cell_b) in player_b.cells.iter().enumerate() {
-                        // 0: nothing happened
-                        // 1: A ate B
-                        // 2: B ate A
-                        match check_who_ate_who(cell_a, cell_b) {
-                            1 => who_ate_who_list
-                                .push(((player_a.id, cell_a_index), (player_b.id, cell_b_index))),
-                            2 => who_ate_who_list
-                                .push(((player_b.id, cell_b_index), (player_a.id, cell_a_index))),
-                            _ => {}
-                        }
+        // one read lock per player for the whole broad phase
+        let mut players = Vec::with_capacity(players_manager.players.len());
+        for player in players_manager.players.values() {
+            players.push(player.read().await);
+        }
+
+        // spawn protected players can't eat nor be eaten, they are left out
+        let now = get_current_timestamp();
+        let players: Vec<&Player> = players
+            .iter()
+            .map(|p| &**p)
+            .filter(|p| !p.is_protected(now))
+            .collect();
+
+        let config = get_current_config();
+        find_who_ate_who(
+            &players,
+            &config.eat_rules,
+            COLLISION_NODE_CAPACITY,
+            config.arena.wrap_size(),
+        )
+    }
+
+    // applies the kills found by get_players_collision, returns the players who lost their
+    // last cell so the caller can remove them
+    async fn handle_kills(
+        &self,
+        players_manager: &PlayerManager,
+        who_ate_who_list: Vec<((PlayerID, usize), (PlayerID, usize))>,
+    ) -> Vec<PlayerID> {
+        let mut players_who_died: Vec<PlayerID> = vec![];
+        for ((player_who_eat, cell_who_eat), (player_eated, cell_eated)) in
+            who_ate_who_list.into_iter()
+        {
+            let mut player_who_eat = match players_manager.players.get(&player_who_eat) {
+                Some(player) => player.write().await,
+                None => continue,
+            };
+
+            let mut player_eated = match players_manager.players.get(&player_eated) {
+                Some(player) => player.write().await,
+                None => continue,
+            };
+
+            if player_eated.has_effect(PowerUpKind::Shield)
+                || !self.mode.can_eat(&player_who_eat, &player_eated)
+            {
+                // the kill is denied, the cells bounce off each other instead
+                if let (Some(cell_a), Some(cell_b)) = (
+                    player_who_eat.cells.get_mut(cell_who_eat),
+                    player_eated.cells.get_mut(cell_eated),
+                ) {
+                    cell_a.push_away(cell_b);
+                }
+                continue;
+            }
+
+            info!(
+                "Player [{:?} {:?}] eat Player [{:?} {:?}]",
+                player_who_eat.id, player_who_eat.name, player_eated.id, player_eated.name
+            );
+
+            let cell_eated_mass = match player_eated.cells.get(cell_eated) {
+                Some(cell_eated) => cell_eated.mass,
+                None => continue,
+            };
+
+            // add mass to the player cell who eated
+            match player_who_eat.cells.get_mut(cell_who_eat) {
+                Some(cell_who_eat) => cell_who_eat.add_mass(cell_eated_mass),
+                None => continue,
+            };
+
+            // remove cell from the player who got eaten
+            player_eated.cells.remove(cell_eated);
+            player_who_eat.recalculate_total_mass();
+            player_eated.recalculate_total_mass();
+
+            // check if player died
+            if player_eated.player_is_dead() {
+                self.mode.on_death(&player_eated, Some(&player_who_eat));
+
+                // player eated socket emit 'RIP', bots have no socket but die all the same
+                if let Some(connection) = self.get_player_stream(player_eated.id).await {
+                    let _ = connection.emit_bi(SendEvent::RIP, ()).await;
+                }
+
+                // io emit 'playerDied' with name of who died, and who killed
+                let _ = self
+                    .emit_bi_broadcast(
+                        SendEvent::PlayerDied,
+                        KillMessage {
+                            killed: player_eated.id,
+                            eater: player_who_eat.id,
+                        },
+                    )
+                    .await;
+
+                let manager = self.amount_manager.lock().await;
+
+                let eaten_id = manager.get_user_id(player_eated.id).unwrap_or_default();
+                let eater_id = manager.get_user_id(player_who_eat.id).unwrap_or_default();
+
+                drop(manager);
+                info!("User ids: {} {}", eaten_id, eater_id);
+
+                let transfer_amount = player_eated.bet.min(player_who_eat.bet);
+
+                //Adding eaten sol amount to eater, unless the mode keeps it in its pot
+                if !self.mode.collect_stake(transfer_amount) {
+                    player_who_eat.total_won += transfer_amount;
+                }
+
+                if player_who_eat.bet < player_eated.bet {
+                    player_eated.total_won += player_eated.bet - transfer_amount;
+                    // Reduce eaten sol amount
+                }
+
+                // TODO: waiting emit at the tick_game is BAD
+
+                // Transferring balance to eaten
+                if player_eated.total_won > 0 {
+                    if self.transfer_balance(eaten_id, player_eated.total_won).await {
+                        // If emit is successful, proceed to clear the data
+                        player_eated.bet = 0;
+                        player_eated.total_won = 0;
                     }
+                } else if !player_eated.bot {
+                    // the whole bet was lost, a restore must not hand it back either
+                    self.record_settlement(eaten_id).await;
                 }
+
+                info!("Player [{:?}] was killed !", player_eated.name);
+
+                // remove player from player_manager
+                players_who_died.push(player_eated.id);
             }
         }
 
-        who_ate_who_list
+        players_who_died
     }
 
     //Trying with gpt less amount of lock from the amount_queue
@@ -643,7 +1667,10 @@ impl Game {
             match message {
                 AmountQueue::AddAmount { id, amount, uid } => {
                     let players_manager = self.player_manager.write().await;
-                    players_manager.set_bet(uid, amount).await;
+                    // the snapshot already holds the bet of a returning player
+                    if !self.restore_player(&players_manager, uid, id).await {
+                        players_manager.set_bet(uid, amount).await;
+                    }
                     manager.set_user_id(uid, id);
                 }
             }
@@ -669,6 +1696,7 @@ impl Game {
     // equivalent to tick_game in node.js backend
     pub async fn tick_game(&self) {
         let mut last_game_loop: i64 = 0;
+        let mut tick: u32 = 0;
         let config = get_current_config();
 
         let instant = Instant::now();
@@ -683,12 +1711,24 @@ impl Game {
             self.handle_queue().await;
             self.handle_amount_queue().await;
 
+            tick = tick.wrapping_add(1);
+            let game_loop_due = (get_current_timestamp() - last_game_loop) >= GAME_LOOP_INTERVAL;
+            if game_loop_due {
+                let fill_to = if self.mode.allows_bots() {
+                    config.bots.fill_to
+                } else {
+                    0
+                };
+                self.balance_bots(fill_to).await;
+            }
+
             debug!("Tick Game AA");
             let players_manager = self.player_manager.read().await;
-            if (get_current_timestamp() - last_game_loop) >= GAME_LOOP_INTERVAL {
+            if game_loop_due {
                 last_game_loop = get_current_timestamp();
                 self.game_loop(&config, &players_manager).await;
             }
+            self.mode.on_tick(get_current_timestamp_micros());
             // let elapsed_game_loop = instant.elapsed() - start;
             debug!("Tick Game B");
 
@@ -697,13 +1737,26 @@ impl Game {
             let mut removed_foods: Vec<FoodID> = vec![];
             let mut removed_mass: Vec<MassFoodID> = vec![];
             let mut removed_virus: Vec<VirusID> = vec![];
+            let mut added_mass: Vec<MassFoodInitData> = vec![];
 
             // execute the mass_move at the MassFoodManager
-            let mass_food_updates = self
-                .mass_food_manager
-                .write()
-                .await
-                .move_food(config.game_width as f32, config.game_height as f32);
+            let mass_food_updates = {
+                let mut mass_food_manager = self.mass_food_manager.write().await;
+                let mut mass_food_updates = mass_food_manager.move_food(
+                    config.game_width as f32,
+                    config.game_height as f32,
+                    config.mass_food.separation,
+                );
+
+                if game_loop_due {
+                    let (expired, decayed) =
+                        mass_food_manager.decay(get_current_timestamp(), &config.mass_food);
+                    removed_mass.extend(expired);
+                    mass_food_updates.extend(decayed);
+                }
+
+                mass_food_updates
+            };
 
             // let elapsed_mass_move = instant.elapsed() - start;
             // execute tick_virus for each virus
@@ -712,21 +1765,30 @@ impl Game {
 
             {
                 let mut virus_manager = self.virus_manager.write().await;
+                let mut virus_room = config.virus.max_count.saturating_sub(virus_manager.count());
 
-                for virus in virus_manager.data.iter_mut() {
-                    let (food_mass_eated, virus_data, shoot_points) = self.tick_virus(virus).await;
+                let mut moved_viruses = vec![];
+                for (index, virus) in virus_manager.data.iter_mut().enumerate() {
+                    let (food_mass_eated, virus_data, shoot_points) =
+                        self.tick_virus(virus, virus_room > 0).await;
 
                     removed_mass.extend(food_mass_eated);
 
                     if let Some(shoot_points) = shoot_points {
                         shoot_virus.push(shoot_points);
+                        virus_room -= 1;
                     }
 
                     if let Some(virus_data) = virus_data {
                         virus_update_data.push(virus_data);
+                        moved_viruses.push(index);
                     }
                 }
 
+                for index in moved_viruses {
+                    virus_manager.reindex(index);
+                }
+
                 let mut new_viruses = vec![];
 
                 for (position, direction) in shoot_virus.into_iter() {
@@ -753,125 +1815,23 @@ impl Game {
 
             // handling collision btw players
             let who_ate_who_list = Self::get_players_collision(&players_manager).await;
-            let mut players_who_died: Vec<PlayerID> = vec![];
-            for ((player_who_eat, cell_who_eat), (player_eated, cell_eated)) in
-                who_ate_who_list.into_iter()
-            {
-                let mut player_who_eat = match players_manager.players.get(&player_who_eat) {
-                    Some(player) => player.write().await,
-                    None => continue,
-                };
-
-                let mut player_eated = match players_manager.players.get(&player_eated) {
-                    Some(player) => player.write().await,
-                    None => continue,
-                };
-
-                info!(
-                    "Player [{:?} {:?}] eat Player [{:?} {:?}]",
-                    player_who_eat.id, player_who_eat.name, player_eated.id, player_eated.name
-                );
-
-                let cell_eated_mass = match player_eated.cells.get(cell_eated) {
-                    Some(cell_eated) => cell_eated.mass,
-                    None => continue,
-                };
-
-                // add mass to the player cell who eated
-                match player_who_eat.cells.get_mut(cell_who_eat) {
-                    Some(cell_who_eat) => cell_who_eat.add_mass(cell_eated_mass),
-                    None => continue,
-                };
-
-                // remove cell from the player who got eaten
-                player_eated.cells.remove(cell_eated);
-
-                // check if player died
-                if player_eated.player_is_dead() {
-                    // player eated socket emit 'RIP'
-                    match self.get_player_stream(player_eated.id).await {
-                        Some(player_eated_connection) => {
-                            let _ = player_eated_connection.emit_bi(SendEvent::RIP, ()).await;
-                        }
-                        None => {
-                            continue;
-                        }
-                    };
-
-                    // io emit 'playerDied' with name of who died, and who killed
-                    let _ = self
-                        .emit_bi_broadcast(
-                            SendEvent::PlayerDied,
-                            KillMessage {
-                                killed: player_eated.id,
-                                eater: player_who_eat.id,
-                            },
-                        )
-                        .await;
-
-                    let manager = self.amount_manager.lock().await;
-
-                    let eaten_id = manager.get_user_id(player_eated.id).unwrap_or_default();
-                    let eater_id = manager.get_user_id(player_who_eat.id).unwrap_or_default();
-
-                    drop(manager);
-                    info!("User ids: {} {}", eaten_id, eater_id);
-
-                    let transfer_amount = player_eated.bet.min(player_who_eat.bet);
-
-                    //Adding eaten sol amount to eater
-                    player_who_eat.total_won += transfer_amount;
-
-                    if player_who_eat.bet < player_eated.bet {
-                        player_eated.total_won += player_eated.bet - transfer_amount;
-                        // Reduce eaten sol amount
-                    }
-
-                    // TODO: waiting emit at the tick_game is BAD
-
-                    // Transferring balance to eaten
-                    if player_eated.total_won > 0 {
-                        let transfer_info = TransferInfo {
-                            id: eaten_id,
-                            amount: player_eated.total_won,
-                            port: self.port,
-                        };
-                        if let Some(ref match_making_socket) = self.matchmaking_socket {
-                            // Emit and await the result
-                            match match_making_socket
-                                .emit(SendEvent::TransferSol, transfer_info)
-                                .await
-                            {
-                                Ok(_) => {
-                                    // If emit is successful, proceed to clear the data
-                                    player_eated.bet = 0;
-                                    player_eated.total_won = 0;
-                                }
-                                Err(e) => {
-                                    // Log the error or handle it appropriately
-                                    error!("Failed to send TransferSol event: {:?}", e);
-                                }
-                            }
-                        } else {
-                            // Optionally handle the case where there is no matchmaking socket
-                            error!("No matchmaking socket available");
-                        }
-                    }
-
-                    info!("Player [{:?}] was killed !", player_eated.name);
-
-                    // remove player from player_manager
-                    players_who_died.push(player_eated.id);
-                }
-            }
+            let mut players_who_died = self
+                .handle_kills(&players_manager, who_ate_who_list)
+                .await;
             // let elapsed_killing_players_tick = instant.elapsed() - start;
 
             debug!("Tick Game E");
+            self.tick_bots(&players_manager, tick).await;
+
+            let now = get_current_timestamp_micros();
+
             for (player_id, player) in players_manager.players.iter() {
                 if players_who_died.contains(player_id) {
                     continue;
                 }
                 let mut player = player.write().await;
+                let was_alive = !player.player_is_dead();
+                added_mass.extend(self.tick_feeding(&mut player, now).await);
                 match self.tick_player(&mut player, &config).await {
                     Some((player_eat_foods, player_eat_mass, player_eat_virus)) => {
                         removed_foods.extend(player_eat_foods);
@@ -881,9 +1841,21 @@ impl Game {
                     }
                     None => {}
                 }
+
+                if was_alive && player.player_is_dead() {
+                    self.eliminate_player(&mut player).await;
+                    players_who_died.push(*player_id);
+                }
             }
             drop(players_manager);
 
+            removed_mass.extend(
+                self.mass_food_manager
+                    .write()
+                    .await
+                    .remove_oldest(config.mass_food.max_count),
+            );
+
             debug!("Tick Game F");
             self.remove_players(players_who_died.iter()).await;
 
@@ -892,6 +1864,7 @@ impl Game {
                 players: players_update_data,
                 virus: virus_update_data,
                 mass_food: mass_food_updates,
+                added_mass,
                 removed_foods,
                 removed_mass,
                 removed_virus,
@@ -941,6 +1914,20 @@ impl Game {
         found_foods
     }
 
+    // the food goes to the food regions of the map when it has some, by density
+    async fn create_foods(&self, amount: usize) -> Vec<FoodData> {
+        let players = self
+            .player_manager
+            .read()
+            .await
+            .collect_and_clone_all_pos()
+            .await;
+
+        self.food_manager
+            .create_foods(amount, &self.mode.spawn_area(), &players)
+            .await
+    }
+
     pub async fn balance_mass(&self, game_mass: TotalMass, max_food: usize, max_virus: usize) {
         // Calculate the total mass based on food and player mass
         let food_count = self.food_manager.get_food_count();
@@ -957,7 +1944,7 @@ impl Game {
 
         // Add food if there is a need
         if food_to_add > 0 {
-            let new_foods_data = self.food_manager.create_many_foods(food_to_add).await;
+            let new_foods_data = self.create_foods(food_to_add).await;
 
             let _ = self
                 .emit_bi_broadcast(
@@ -969,10 +1956,30 @@ impl Game {
                 .await;
         }
 
+        let power_ups_to_add = {
+            let mut power_up_manager = self.power_up_manager.write().await;
+            let power_ups_to_add = get_current_config()
+                .power_ups
+                .max
+                .saturating_sub(power_up_manager.count());
+
+            power_up_manager.create_many(power_ups_to_add, &self.mode.spawn_area())
+        };
+
+        if !power_ups_to_add.is_empty() {
+            self.emit_bi_broadcast(
+                SendEvent::PowerUpsAdded,
+                PowerUpsAddedMessage {
+                    power_ups: power_ups_to_add,
+                },
+            )
+            .await;
+        }
+
         let mut virus_manager = self.virus_manager.write().await;
         let viruses_to_add = max_virus
-            .checked_sub(virus_manager.count())
-            .unwrap_or_default();
+            .min(get_current_config().virus.max_count)
+            .saturating_sub(virus_manager.count());
 
         if viruses_to_add > 0 {
             let new_virus_data = virus_manager.create_many_virus(viruses_to_add);
@@ -990,36 +1997,21 @@ impl Game {
 
     pub async fn enumerate_what_player_sees(&self, player: &Player) -> VisibleEntities {
         let visible_food = self.get_food_in_view(player).await;
+        let visible_zone = player.get_visible_area();
 
         // Get visible viruses
         let visible_viruses = self
             .virus_manager
             .read()
             .await
-            .data
-            .iter()
-            .filter_map(|virus| {
-                if is_visible_entity(virus.get_position(), player) {
-                    return Some(virus.clone());
-                }
-                None
-            })
-            .collect();
+            .retrieve(&visible_zone);
 
         // Get visible mass food
         let visible_mass_food = self
             .mass_food_manager
             .read()
             .await
-            .data
-            .iter()
-            .filter_map(|mass| {
-                if is_visible_entity(mass.point, player) {
-                    return Some(mass.clone());
-                }
-                None
-            })
-            .collect();
+            .retrieve(&visible_zone);
 
         // Get visible players
         // let mut visible_players: Vec<PlayerInitData> = vec![];
@@ -1045,3 +2037,47 @@ impl Game {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, sync::Arc};
+
+    use socketioxide::SocketIo;
+    use tokio::sync::Mutex;
+
+    use super::Game;
+
+    #[tokio::test]
+    async fn test_eaten_bots_are_replaced() {
+        let (_, io_socket) = SocketIo::new_layer();
+        let game = Game::new(io_socket, None, Arc::new(Mutex::new(VecDeque::new())));
+
+        game.balance_bots(2).await;
+        let bots: Vec<_> = game.player_manager.read().await.players.keys().copied().collect();
+        assert_eq!(bots.len(), 2);
+        let (eater, eaten) = (bots[0], bots[1]);
+
+        let died = {
+            let players_manager = game.player_manager.read().await;
+            let mut bot = players_manager.players[&eater].write().await;
+            bot.cells[0].set_mass(500);
+            bot.recalculate_total_mass();
+            drop(bot);
+
+            // bots have no socket, the kill still goes through
+            game.handle_kills(&players_manager, vec![((eater, 0), (eaten, 0))])
+                .await
+        };
+        assert_eq!(died, vec![eaten]);
+
+        game.remove_players(died.iter()).await;
+        assert_eq!(game.player_manager.read().await.players.len(), 1);
+
+        game.balance_bots(2).await;
+        let players_manager = game.player_manager.read().await;
+        assert_eq!(players_manager.players.len(), 2);
+        for bot in players_manager.players.values() {
+            assert!(!bot.read().await.player_is_dead());
+        }
+    }
+}
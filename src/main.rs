@@ -1,10 +1,13 @@
+mod bot;
 mod config;
 mod game;
 mod managers;
 mod map;
+mod modes;
 mod player_connection;
 mod recv_messages;
 mod send_messages;
+mod snapshot;
 mod utils;
 
 use axum_server::tls_rustls::RustlsConfig;
@@ -14,14 +17,15 @@ use game::Game;
 use map::player::Player;
 use player_connection::PlayerConnection;
 use recv_messages::{
-    AmountMessage, AnyEventPacket, ChatMessage, LetMeInMessage, RecvEvent, TargetMessage,
-    UserIdMessage,
+    AdminCommandMessage, AmountMessage, AnyEventPacket, ChatMessage, FreezeMessage,
+    LetMeInMessage, RecvEvent, SplitMessage, TargetMessage, UserIdMessage,
 };
 use rust_socketio::asynchronous::{Client, ClientBuilder};
 use rust_socketio::Payload;
-use send_messages::{MassFoodAddedMessage, PlayerJoinMessage, SendEvent, WelcomeMessage};
+use send_messages::{PlayerJoinMessage, SendEvent, WelcomeMessage};
 use time::OffsetDateTime;
 use tokio::select;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::{Mutex, RwLock};
 //Debugging
 use dotenv::dotenv;
@@ -58,6 +62,10 @@ use socketioxide::{
     extract::{Data, SocketRef},
     SocketIo,
 };
+use tokio_timerfd::sleep;
+
+// seconds between two settlement attempts of a drain that could not pay everyone
+const DRAIN_RETRY_INTERVAL: u64 = 10;
 
 #[derive(Parser)]
 #[command(about, long_about = None)]
@@ -224,6 +232,26 @@ async fn start_webtransport_server(game_ref: Arc<Game>, server_port: u16) -> any
     Ok(())
 }
 
+// money still at stake keeps the server up, the settlements are retried until they all go through
+async fn drain_and_exit(game_ref: Arc<Game>) {
+    if game_ref.is_draining() {
+        return;
+    }
+
+    let mut settled = game_ref.drain().await;
+    while !settled {
+        error!(
+            "Unsettled players left, retrying in {}s",
+            DRAIN_RETRY_INTERVAL
+        );
+        let _ = sleep(Duration::from_secs(DRAIN_RETRY_INTERVAL)).await;
+        settled = game_ref.settle_all().await;
+    }
+
+    info!("Exiting after drain");
+    std::process::exit(0);
+}
+
 trait WebTransportEmit {
     // async fn wt_bi_emit(&mut self, send_event: SendEvent, data: &str) {}
     async fn wt_bi_emit<T: serde::Serialize>(&mut self, send_event: SendEvent, data: T) {}
@@ -383,37 +411,32 @@ async fn handle_connection(
                         player.target_y = data.target.y;
                     }
                     RecvEvent::PlayerSendingMass => {
-                        let config = get_current_config();
-                        let mut player = player_ref.write().await;
+                        player_ref.write().await.feed_requested = true;
+                    }
 
-                        if player.total_mass < config.min_cell_mass() as usize {
+                    RecvEvent::StartFeeding => {
+                        player_ref.write().await.feeding = true;
+                    }
+
+                    RecvEvent::StopFeeding => {
+                        player_ref.write().await.feeding = false;
+                    }
+
+                    RecvEvent::Freeze => {
+                        if packet.value.is_none() {
                             continue;
                         }
 
-                        let player_position = player.get_position_point();
-                        let player_target = player.get_target_point();
-                        let player_hue = player.hue;
-
-                        let mut mass_food_manager = game_ref.mass_food_manager.write().await;
-                        for cell in player.cells.iter_mut() {
-                            if cell.mass >= config.min_cell_mass() {
-                                cell.remove_mass(config.fire_food);
-                                let mass_food_init_data = mass_food_manager.add_new(
-                                    &player_position,
-                                    &player_target,
-                                    &cell.position,
-                                    player_hue,
-                                    config.fire_food,
-                                );
-
-                                let _ = game_ref
-                                    .emit_bi_broadcast(
-                                        SendEvent::MassFoodAdded,
-                                        MassFoodAddedMessage(mass_food_init_data),
-                                    )
-                                    .await;
-                            }
-                        }
+                        let data: FreezeMessage =
+                            match serde_json::from_value(packet.value.unwrap()) {
+                                Ok(d) => d,
+                                Err(err) => {
+                                    error!("Error parsing packet [FreezeMessage]: {:?}", err);
+                                    continue;
+                                }
+                            };
+
+                        player_ref.write().await.frozen = data.frozen;
                     }
 
                     RecvEvent::Cashout => {
@@ -421,25 +444,30 @@ async fn handle_connection(
                     }
 
                     RecvEvent::Teleport => {
-                        let points = game_ref
-                            .player_manager
-                            .read()
-                            .await
-                            .collect_and_clone_all_pos()
-                            .await;
-                        let spawn_point = game_ref.create_player_spawn_point(points);
-
-                        {
-                            let mut player = player_ref.write().await;
-                            player.teleport(&spawn_point);
-                        }
+                        game_ref.teleport_player(player_ref.clone()).await;
                     }
                     RecvEvent::PlayerSplit => {
                         let config = get_current_config();
 
+                        let count = match packet.value {
+                            Some(value) => match serde_json::from_value::<SplitMessage>(value) {
+                                Ok(data) => data.count,
+                                Err(err) => {
+                                    error!("Error parsing packet [SplitMessage]: {:?}", err);
+                                    continue;
+                                }
+                            },
+                            None => 1,
+                        };
+
+                        // all the splits happen under the same lock, no tick in between
                         {
                             let mut player = player_ref.write().await;
-                            player.user_split(config.limit_split as usize, config.split_min_mass);
+                            player.user_split_many(
+                                count,
+                                config.limit_split as usize,
+                                config.split_min_mass,
+                            );
                         }
 
                         let _ = player_connection
@@ -464,6 +492,47 @@ async fn handle_connection(
                             .emit_bi_broadcast(SendEvent::PlayerMessage, data)
                             .await;
                     }
+                    RecvEvent::AdminCommand => {
+                        if packet.value.is_none() {
+                            continue;
+                        }
+
+                        let data: AdminCommandMessage =
+                            match serde_json::from_value(packet.value.unwrap()) {
+                                Ok(d) => d,
+                                Err(err) => {
+                                    error!("Error parsing packet [AdminCommandMessage]: {:?}", err);
+                                    continue;
+                                }
+                            };
+
+                        let player_id = player_ref.read().await.id;
+                        match &get_current_config().admin_pass {
+                            Some(admin_pass) if *admin_pass == data.password => {}
+                            Some(_) => {
+                                warn!("Wrong admin password from player[{}]", player_id);
+                                continue;
+                            }
+                            None => {
+                                warn!("Admin command from player[{}] refused, no ADMIN_PASS set", player_id);
+                                continue;
+                            }
+                        }
+
+                        match data.command.as_str() {
+                            "snapshot" => {
+                                game_ref.save_snapshot().await;
+                            }
+                            // same path as SIGTERM, runs on its own so this connection can close
+                            "drain" => {
+                                info!("Drain requested by player[{}]", player_id);
+                                tokio::spawn(drain_and_exit(game_ref.clone()));
+                            }
+                            command => {
+                                warn!("Unknown admin command: {}", command);
+                            }
+                        }
+                    }
                     _ => {}
                 }
             } else {
@@ -472,6 +541,12 @@ async fn handle_connection(
                         if packet.value.is_none() {
                             continue;
                         }
+                        if game_ref.is_draining() {
+                            let _ = player_connection
+                                .emit_bi(SendEvent::KickPlayer, "Server is closing.")
+                                .await;
+                            continue;
+                        }
                         let data: LetMeInMessage =
                             match serde_json::from_value(packet.value.unwrap()) {
                                 Ok(d) => d,
@@ -533,6 +608,7 @@ async fn handle_connection(
                             player.setup(data.name, data.img_url);
                         }
                         let start = game_ref.game_start;
+                        let round_end = game_ref.round_manager.lock().await.get_round_end();
                         let _ = player_connection
                             .emit_bi(
                                 SendEvent::Welcome,
@@ -543,6 +619,8 @@ async fn handle_connection(
                                     default_mass_food: config.food_mass,
                                     default_mass_mass_food: config.fire_food,
                                     start: start,
+                                    round_end,
+                                    map: config.arena.clone(),
                                 },
                             )
                             .await;
@@ -583,6 +661,7 @@ async fn handle_connection(
 
                         info!("Player[{:?} / {}] joined", player.name, player.id);
                         //MARK: Added newly
+                        // a snapshot is only handed back once the matchmaking server answers
                         if let Some(socket_mtchmkng) = &game_ref.matchmaking_socket {
                             if let Some(ref user_id) = data.user_id {
                                 info!("User id game received {}", user_id);
@@ -815,6 +894,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         match_making_socket,
         shared_queue,
     ));
+    game.restore_snapshot().await;
     let game_cloned = game.clone();
 
     // tokio spawn game loop
@@ -822,11 +902,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         game_cloned.tick_game().await;
     });
 
+    let game_cloned = game.clone();
+    tokio::spawn(async move {
+        game_cloned.snapshot_loop().await;
+    });
+
     let game_cloned = game.clone();
     let server_port: u16 = *get_server_port();
 
     tokio::spawn(start_webtransport_server(game_cloned.clone(), server_port));
 
+    let game_cloned = game.clone();
+    tokio::spawn(async move {
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+                info!("SIGTERM received");
+                drain_and_exit(game_cloned).await;
+            }
+            Err(err) => {
+                error!("Error listening for SIGTERM: {:?}", err);
+            }
+        }
+    });
+
     info!("Game started! Waiting for players");
 
     let compression_layer: CompressionLayer = CompressionLayer::new().deflate(true);
@@ -1,32 +1,104 @@
-use std::sync::atomic::AtomicUsize;
+use std::{
+    cmp::Reverse,
+    f32::consts::TAU,
+    sync::{atomic::AtomicUsize, Mutex},
+};
 
 use log::debug;
+use rand::{rngs::StdRng, Rng};
 use tokio::sync::RwLock;
 
 use crate::{
     config::get_current_config, map::{
+        arena::FoodDistribution,
         food::{Food, FoodData},
-        point::Point,
+        point::{AsPoint, Point},
     }, utils::{
         consts::Mass,
         id::id_from_position,
-        quad_tree::QuadTree,
-        util::{create_random_number_u32, mass_to_radius},
+        quad_tree::{QuadTree, Rectangle},
+        util::{create_rng, mass_to_radius},
     }
 };
 
+// tries to find a spot for one food before skipping it
+const SPAWN_ATTEMPTS: usize = 30;
+
+// random source of the food placement, the feeding grounds stay put until the next clear
+struct FoodSpawner {
+    rng: StdRng,
+    grounds: Vec<Point>,
+}
+
+impl FoodSpawner {
+    // a random point following the distribution, it can fall outside of `area`
+    fn candidate(&mut self, distribution: &FoodDistribution, area: &Rectangle) -> (f32, f32) {
+        match distribution {
+            FoodDistribution::Clustered { grounds, spread } if *grounds > 0 => {
+                if self.grounds.is_empty() {
+                    let bounds = get_current_config().arena.bounds();
+                    self.grounds = (0..*grounds)
+                        .map(|_| Point {
+                            x: self.rng.gen_range(bounds.x..bounds.x + bounds.w),
+                            y: self.rng.gen_range(bounds.y..bounds.y + bounds.h),
+                            radius: 0.0,
+                        })
+                        .collect();
+                }
+
+                // normal distribution around the ground (box-muller)
+                let ground = self.grounds[self.rng.gen_range(0..self.grounds.len())];
+                let distance = spread * (-2.0 * self.rng.gen_range(f32::EPSILON..1.0).ln()).sqrt();
+                let angle = self.rng.gen_range(0.0..TAU);
+                (ground.x + distance * angle.cos(), ground.y + distance * angle.sin())
+            }
+            _ => (
+                self.rng.gen_range(area.x..area.x + area.w),
+                self.rng.gen_range(area.y..area.y + area.h),
+            ),
+        }
+    }
+}
+
+// the checks of the distribution that depend on what is already on the map
+fn is_free_spot(
+    distribution: &FoodDistribution,
+    position: &Point,
+    quad_tree: &QuadTree<Food>,
+    players: &[Point],
+) -> bool {
+    match distribution {
+        FoodDistribution::PoissonDisk { min_distance } => {
+            let mut near_foods = vec![];
+            quad_tree.retrieve(&Rectangle::around(position, *min_distance), &mut near_foods);
+            near_foods
+                .iter()
+                .all(|food| position.distance(&food.as_point()) >= *min_distance)
+        }
+        FoodDistribution::AvoidPlayers { distance } => players
+            .iter()
+            .all(|player| position.distance(player) >= player.radius + distance),
+        _ => true,
+    }
+}
+
 pub struct FoodManager {
     default_food_mass: Mass,
-    pub quad_tree: RwLock<QuadTree>,
+    pub quad_tree: RwLock<QuadTree<Food>>,
     food_count: AtomicUsize,
+    spawner: Mutex<FoodSpawner>,
 }
 
 impl FoodManager {
-    pub fn new(food_mass: Mass, quad_tree: QuadTree) -> Self {
+    pub fn new(food_mass: Mass, quad_tree: QuadTree<Food>) -> Self {
         FoodManager {
             default_food_mass: food_mass,
             quad_tree: RwLock::new(quad_tree),
             food_count: AtomicUsize::new(0),
+            spawner: Mutex::new(FoodSpawner {
+                rng: create_rng(get_current_config().rng_seed),
+                grounds: vec![],
+            }),
         }
     }
 
@@ -51,43 +123,91 @@ impl FoodManager {
         self.set_food_count(self.get_food_count() + add_amount);
     }
 
-    pub async fn create_many_foods(&self, food_amount: usize) -> Vec<FoodData> {
-        let config = get_current_config();
-        
-        let min_x = (mass_to_radius(config.food_mass)) as u16;
-        let max_x = ((config.game_width as f32) - mass_to_radius(config.food_mass)) as u16;
-        let min_y = (mass_to_radius(config.food_mass)) as u16;
-        let max_y = ((config.game_height as f32) - mass_to_radius(config.food_mass)) as u16;
+    // splits the amount over the food regions of the arena, or places everything in `spawn_area`
+    pub async fn create_foods(
+        &self,
+        amount: usize,
+        spawn_area: &Rectangle,
+        players: &[Point],
+    ) -> Vec<FoodData> {
+        let arena = &get_current_config().arena;
 
+        if arena.food_regions.is_empty() {
+            return self.create_many_foods(amount, spawn_area, players).await;
+        }
 
-        let mut new_foods_data: Vec<FoodData> = vec![];
-        let mut quad_tree = self.quad_tree.write().await;
+        let mut per_region = vec![0; arena.food_regions.len()];
+        {
+            let mut spawner = self.spawner.lock().unwrap();
+            for _ in 0..amount {
+                if let Some(region) = arena.pick_food_region(&mut spawner.rng) {
+                    per_region[region] += 1;
+                }
+            }
+        }
+
+        let mut new_foods_data = vec![];
+        for (region, amount) in arena.food_regions.iter().zip(per_region) {
+            if amount == 0 {
+                continue;
+            }
+            if let Some(area) = region.area.intersection(spawn_area) {
+                new_foods_data.extend(self.create_many_foods(amount, &area, players).await);
+            }
+        }
+
+        new_foods_data
+    }
 
+    // new foods are placed inside `area` following the distribution of the arena, a food
+    // that finds no valid spot is skipped
+    pub async fn create_many_foods(
+        &self,
+        food_amount: usize,
+        area: &Rectangle,
+        players: &[Point],
+    ) -> Vec<FoodData> {
+        let config = get_current_config();
         let radius = mass_to_radius(self.default_food_mass);
-        for _ in 0..food_amount {
-            let mut food_id;
-            let position;
 
-            loop {
-                let x = create_random_number_u32(min_x, max_x);
-                let y = create_random_number_u32(min_y, max_y);
+        let min_x = (area.x + mass_to_radius(config.food_mass)) as u16;
+        let max_x = (area.x + area.w - mass_to_radius(config.food_mass)) as u16;
+        let min_y = (area.y + mass_to_radius(config.food_mass)) as u16;
+        let max_y = (area.y + area.h - mass_to_radius(config.food_mass)) as u16;
 
-                food_id = id_from_position(x, y);
+        if min_x >= max_x || min_y >= max_y {
+            return vec![];
+        }
 
-                if quad_tree.contains_food(food_id) {
-                    continue;
+        let mut new_foods_data: Vec<FoodData> = vec![];
+        let mut quad_tree = self.quad_tree.write().await;
+        let mut spawner = self.spawner.lock().unwrap();
+
+        for _ in 0..food_amount {
+            let candidate = (0..SPAWN_ATTEMPTS).find_map(|_| {
+                let (x, y) = spawner.candidate(&config.arena.food_distribution, area);
+                if x < min_x as f32 || x >= max_x as f32 || y < min_y as f32 || y >= max_y as f32 {
+                    return None;
                 }
 
-                position = Point {
+                let (x, y) = (x as u16, y as u16);
+                let position = Point {
                     x: x as f32,
                     y: y as f32,
                     radius,
                 };
-                break;
-            }
+                let food_id = id_from_position(x, y);
 
-            let food = Food::new(food_id, &position);
-            
+                (!quad_tree.contains(food_id)
+                    && is_free_spot(&config.arena.food_distribution, &position, &quad_tree, players))
+                .then_some((food_id, position))
+            });
+
+            let Some((food_id, position)) = candidate else {
+                continue;
+            };
+
+            let food = Food::new(food_id, &position, &mut spawner.rng);
             if quad_tree.insert(food) {
                 new_foods_data.push(food.generate_data());
             } else {
@@ -95,11 +215,29 @@ impl FoodManager {
             }
         }
 
-        self.add_food_count(food_amount);
+        self.add_food_count(new_foods_data.len());
 
         new_foods_data
     }
 
+    // random spawn points of the arena, the ones with the most food around come first
+    pub async fn richest_spots(&self, amount: usize, range: f32, radius: f32) -> Vec<Point> {
+        let arena = &get_current_config().arena;
+        let quad_tree = self.quad_tree.read().await;
+
+        let mut spots: Vec<(usize, Point)> = (0..amount)
+            .map(|_| {
+                let spot = arena.random_spawn_point(radius);
+                let mut foods = vec![];
+                quad_tree.retrieve(&Rectangle::around(&spot, range), &mut foods);
+                (foods.len(), spot)
+            })
+            .collect();
+
+        spots.sort_by_key(|(foods, _)| Reverse(*foods));
+        spots.into_iter().map(|(_, spot)| spot).collect()
+    }
+
     pub async fn delete_many_foods(&self, foods_to_delete: impl Iterator<Item=&Food>) {
         let mut quad_tree = self.quad_tree.write().await;
         let mut length = 0;
@@ -111,13 +249,108 @@ impl FoodManager {
         self.sub_food_count(length);
     }
 
+    pub async fn clear(&self) {
+        self.quad_tree.write().await.clear();
+        self.set_food_count(0);
+        self.spawner.lock().unwrap().grounds.clear();
+    }
+
+    pub async fn restore(&self, foods: Vec<Food>) {
+        let mut quad_tree = self.quad_tree.write().await;
+        quad_tree.clear();
+
+        let mut length = 0;
+        for food in foods {
+            if quad_tree.insert(food) {
+                length += 1;
+            }
+        }
+        self.set_food_count(length);
+    }
+
+    pub async fn get_all_foods(&self) -> Vec<Food> {
+        self.quad_tree
+            .read()
+            .await
+            .get_all()
+            .into_iter()
+            .copied()
+            .collect()
+    }
+
     pub async fn get_foods_init_data(&self) -> Vec<FoodData> {
         let mut foods_data = vec![];
 
-        for food in self.quad_tree.read().await.get_all_foods() {
+        for food in self.quad_tree.read().await.get_all() {
             foods_data.push(food.generate_data());
         }
 
         foods_data
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        map::{arena::FoodDistribution, food::Food, point::Point},
+        utils::{
+            quad_tree::{QuadTree, Rectangle},
+            util::create_rng,
+        },
+    };
+
+    use super::{is_free_spot, FoodSpawner};
+
+    fn spawner(seed: u64) -> FoodSpawner {
+        FoodSpawner {
+            rng: create_rng(Some(seed)),
+            grounds: vec![],
+        }
+    }
+
+    fn point(x: f32, y: f32) -> Point {
+        Point { x, y, radius: 5.0 }
+    }
+
+    #[test]
+    fn test_same_seed_same_food_layout() {
+        let area = Rectangle::new(0.0, 0.0, 1_000.0, 1_000.0);
+        let clustered = FoodDistribution::Clustered {
+            grounds: 3,
+            spread: 50.0,
+        };
+
+        let (mut a, mut b) = (spawner(7), spawner(7));
+        for distribution in [FoodDistribution::Uniform, clustered] {
+            for _ in 0..20 {
+                assert_eq!(
+                    a.candidate(&distribution, &area),
+                    b.candidate(&distribution, &area)
+                );
+            }
+        }
+        assert_eq!(a.grounds.len(), 3);
+    }
+
+    #[test]
+    fn test_distributions_reject_crowded_spots() {
+        let mut quad_tree = QuadTree::new(Rectangle::new(0.0, 0.0, 1_000.0, 1_000.0), 4);
+        quad_tree.insert(Food::new(0, &point(100.0, 100.0), &mut create_rng(Some(1))));
+
+        let poisson = FoodDistribution::PoissonDisk {
+            min_distance: 50.0,
+        };
+        assert!(!is_free_spot(&poisson, &point(130.0, 100.0), &quad_tree, &[]));
+        assert!(is_free_spot(&poisson, &point(160.0, 100.0), &quad_tree, &[]));
+
+        let avoid_players = FoodDistribution::AvoidPlayers { distance: 100.0 };
+        let players = [Point {
+            x: 500.0,
+            y: 500.0,
+            radius: 50.0,
+        }];
+        assert!(!is_free_spot(&avoid_players, &point(600.0, 500.0), &quad_tree, &players));
+        assert!(is_free_spot(&avoid_players, &point(700.0, 500.0), &quad_tree, &players));
+        assert!(is_free_spot(&FoodDistribution::Uniform, &point(100.0, 100.0), &quad_tree, &players));
+    }
+}
@@ -1,21 +1,36 @@
+use std::cmp::Reverse;
+
 use crate::{
+    config::MassFoodConfig,
     map::{
         mass_food::{MassFood, MassFoodInitData, MassFoodUpdateData},
         point::Point,
     },
-    utils::{consts::Mass, id::MassFoodID},
+    utils::{
+        consts::{Mass, TotalMass},
+        game_logic::keep_in_arena,
+        id::MassFoodID,
+        quad_tree::{QuadTree, Rectangle},
+    },
 };
 
-#[derive(Default, Debug)]
+// moves one entry of the index to where the mass food is now
+fn reindex(quad_tree: &mut QuadTree<MassFood>, mass_food: &MassFood) {
+    quad_tree.remove_by_id(mass_food.id);
+    quad_tree.insert(mass_food.clone());
+}
+
 pub struct MassFoodManager {
     pub data: Vec<MassFood>,
+    quad_tree: QuadTree<MassFood>,
     id_counter: MassFoodID,
 }
 
 impl MassFoodManager {
-    pub fn new() -> Self {
+    pub fn new(quad_tree: QuadTree<MassFood>) -> Self {
         MassFoodManager {
             data: Vec::new(),
+            quad_tree,
             id_counter: 0,
         }
     }
@@ -25,6 +40,15 @@ impl MassFoodManager {
         self.id_counter
     }
 
+    // only for restore and clear, everything else moves single entries in the index
+    fn rebuild_index(&mut self) {
+        self.quad_tree.clear();
+
+        for mass_food in self.data.iter() {
+            self.quad_tree.insert(mass_food.clone());
+        }
+    }
+
     pub fn add_new(
         &mut self,
         player_position: &Point,
@@ -43,35 +67,334 @@ impl MassFoodManager {
             mass,
         );
         let mass_food_init_data = mass_food.generate_init_data();
+        self.quad_tree.insert(mass_food.clone());
         self.data.push(mass_food);
 
         mass_food_init_data
     }
 
-    //moves the mass until the speed is 0
-    pub fn move_food(&mut self, game_width: f32, game_height: f32) -> Vec<MassFoodUpdateData> {
-        self.data
+    //moves the mass until the speed is 0, then resting blobs push each other apart
+    pub fn move_food(
+        &mut self,
+        game_width: f32,
+        game_height: f32,
+        separation: f32,
+    ) -> Vec<MassFoodUpdateData> {
+        let quad_tree = &mut self.quad_tree;
+        let mut updates: Vec<MassFoodUpdateData> = self
+            .data
             .iter_mut()
             .filter_map(|mass_food| {
                 if mass_food.speed.is_some() {
                     mass_food.move_self(game_width, game_height);
+                    reindex(quad_tree, mass_food);
                     return Some(mass_food.generate_update_data());
                 }
                 None
             })
+            .collect();
+
+        updates.extend(self.separate(game_width, game_height, separation));
+        updates
+    }
+
+    // every resting blob moves away from the blobs it overlaps, piles spread out over a few ticks
+    fn separate(
+        &mut self,
+        game_width: f32,
+        game_height: f32,
+        separation: f32,
+    ) -> Vec<MassFoodUpdateData> {
+        if separation <= 0.0 {
+            return vec![];
+        }
+
+        let mut pushes = vec![];
+        for (index, mass_food) in self.data.iter().enumerate() {
+            if mass_food.speed.is_some() {
+                continue;
+            }
+
+            let point = mass_food.point;
+            let mut push = Point {
+                x: 0.0,
+                y: 0.0,
+                radius: 0.0,
+            };
+
+            for other in self.retrieve(&Rectangle::around(&point, point.radius)) {
+                if other.id == mass_food.id {
+                    continue;
+                }
+
+                let overlap = point.radius + other.point.radius - point.distance(&other.point);
+                if overlap <= 0.0 {
+                    continue;
+                }
+
+                let away = Point {
+                    x: point.x - other.point.x,
+                    y: point.y - other.point.y,
+                    radius: 0.0,
+                };
+                // stacked on the exact same spot, the ids pick the way out
+                let away = if away.x == 0.0 && away.y == 0.0 {
+                    let angle = (mass_food.id as f32) - (other.id as f32);
+                    Point {
+                        x: angle.cos(),
+                        y: angle.sin(),
+                        radius: 0.0,
+                    }
+                } else {
+                    away.normalize()
+                };
+
+                push.x += away.x * overlap * separation / 2.0;
+                push.y += away.y * overlap * separation / 2.0;
+            }
+
+            if push.x != 0.0 || push.y != 0.0 {
+                pushes.push((index, push));
+            }
+        }
+
+        pushes
+            .into_iter()
+            .map(|(index, push)| {
+                let mass_food = &mut self.data[index];
+                mass_food.point.x += push.x;
+                mass_food.point.y += push.y;
+                keep_in_arena(
+                    &mut mass_food.point.x,
+                    &mut mass_food.point.y,
+                    mass_food.point.radius,
+                    5.0,
+                    game_width,
+                    game_height,
+                );
+                reindex(&mut self.quad_tree, mass_food);
+                mass_food.generate_update_data()
+            })
             .collect()
     }
 
+    // shrinks the old blobs, returns the expired ones and the updates of those that shrunk
+    pub fn decay(
+        &mut self,
+        now: i64,
+        config: &MassFoodConfig,
+    ) -> (Vec<MassFoodID>, Vec<MassFoodUpdateData>) {
+        let mut expired = vec![];
+        let mut updates = vec![];
+
+        let quad_tree = &mut self.quad_tree;
+        self.data.retain_mut(|mass_food| {
+            let mass = mass_food.mass;
+            if !mass_food.decay(now, config) {
+                quad_tree.remove_by_id(mass_food.id);
+                expired.push(mass_food.id);
+                return false;
+            }
+            if mass_food.mass != mass {
+                // the radius shrunk with the mass
+                reindex(quad_tree, mass_food);
+                updates.push(mass_food.generate_update_data());
+            }
+            true
+        });
+
+        (expired, updates)
+    }
+
+    // the data is kept in creation order, the oldest blobs go first
+    pub fn remove_oldest(&mut self, max_count: usize) -> Vec<MassFoodID> {
+        let excess = self.data.len().saturating_sub(max_count);
+        if excess == 0 {
+            return vec![];
+        }
+
+        let removed: Vec<MassFoodID> =
+            self.data.drain(..excess).map(|mass_food| mass_food.id).collect();
+        for mass_id in removed.iter() {
+            self.quad_tree.remove_by_id(*mass_id);
+        }
+        removed
+    }
+
     pub fn remove_food(&mut self, mass_id: MassFoodID) {
         match self.data.iter().position(|x| x.id == mass_id) {
             Some(index) => {
                 self.data.remove(index);
+                self.quad_tree.remove_by_id(mass_id);
             }
             None => {}
         }
     }
 
+    // every mass food touching the range
+    pub fn retrieve(&self, range: &Rectangle) -> Vec<MassFood> {
+        let mut found = vec![];
+        self.quad_tree.retrieve(range, &mut found);
+        found
+    }
+
+    pub fn restore(&mut self, mass_foods: Vec<MassFood>) {
+        if let Some(max_id) = mass_foods.iter().map(|m| m.id).max() {
+            self.id_counter = max_id;
+        }
+        self.data = mass_foods;
+        self.rebuild_index();
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.rebuild_index();
+    }
+
+    // centers of the piles of ejected mass holding at least min_mass within range, biggest first
+    pub fn find_blobs(&self, range: f32, min_mass: Mass) -> Vec<Point> {
+        let mut blobs: Vec<(TotalMass, Point)> = vec![];
+
+        for mass_food in self.data.iter() {
+            let around = self.retrieve(&Rectangle::around(&mass_food.point, range));
+            let total_mass: TotalMass = around.iter().map(|other| other.mass as TotalMass).sum();
+            if total_mass < min_mass as TotalMass {
+                continue;
+            }
+
+            // mass weighted center, relative to this blob so it works through a wrapping edge
+            let (mut x, mut y) = (0.0, 0.0);
+            for other in around.iter() {
+                let (dx, dy) = mass_food.point.delta_to(&other.point);
+                x += dx * other.mass as f32;
+                y += dy * other.mass as f32;
+            }
+            let center = Point {
+                x: mass_food.point.x + x / total_mass as f32,
+                y: mass_food.point.y + y / total_mass as f32,
+                radius: 0.0,
+            };
+            blobs.push((total_mass, center));
+        }
+
+        blobs.sort_by_key(|(mass, _)| Reverse(*mass));
+
+        // one center per pile
+        let mut centers: Vec<Point> = vec![];
+        for (_, center) in blobs {
+            if centers.iter().all(|other| other.distance(&center) > range) {
+                centers.push(center);
+            }
+        }
+        centers
+    }
+
     pub fn get_mass_food_init_data(&self) -> Vec<MassFoodInitData> {
         self.data.iter().map(|m| m.generate_init_data()).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::MassFoodConfig,
+        map::point::Point,
+        utils::quad_tree::{QuadTree, Rectangle},
+    };
+
+    use super::MassFoodManager;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point { x, y, radius: 0.0 }
+    }
+
+    // resting blobs ejected at the same spot
+    fn manager_with_pile(count: usize) -> MassFoodManager {
+        let mut manager =
+            MassFoodManager::new(QuadTree::new(Rectangle::new(0.0, 0.0, 1_000.0, 1_000.0), 8));
+        for _ in 0..count {
+            manager.add_new(&point(500.0, 500.0), &point(1.0, 0.0), &point(500.0, 500.0), 0, 20);
+        }
+        for mass_food in manager.data.iter_mut() {
+            mass_food.speed = None;
+        }
+        manager
+    }
+
+    #[test]
+    fn test_oldest_mass_food_is_removed_first() {
+        let mut manager = manager_with_pile(5);
+        let ids: Vec<_> = manager.data.iter().map(|m| m.id).collect();
+
+        assert!(manager.remove_oldest(5).is_empty());
+        assert_eq!(manager.remove_oldest(3), ids[..2]);
+        assert_eq!(manager.data.len(), 3);
+        assert_eq!(manager.data[0].id, ids[2]);
+    }
+
+    #[test]
+    fn test_mass_food_decays_then_expires() {
+        let config = MassFoodConfig::default();
+        let mut manager = manager_with_pile(1);
+        let created_at = manager.data[0].created_at;
+
+        let (expired, updates) = manager.decay(created_at + config.decay_start, &config);
+        assert!(expired.is_empty() && updates.is_empty());
+
+        let mut mass = manager.data[0].mass;
+        for age in config.decay_start + 1..config.lifetime {
+            let (expired, _) = manager.decay(created_at + age, &config);
+            if !expired.is_empty() {
+                break;
+            }
+            assert!(manager.data[0].mass <= mass);
+            mass = manager.data[0].mass;
+        }
+
+        manager.decay(created_at + config.lifetime, &config);
+        assert!(manager.data.is_empty());
+    }
+
+    #[test]
+    fn test_piled_mass_food_spreads_out() {
+        let mut manager = manager_with_pile(4);
+
+        for _ in 0..50 {
+            manager.move_food(1_000.0, 1_000.0, 0.5);
+        }
+
+        for (i, a) in manager.data.iter().enumerate() {
+            for b in manager.data[i + 1..].iter() {
+                let overlap = a.point.radius + b.point.radius - a.point.distance(&b.point);
+                assert!(overlap < 1.0, "{} and {} still overlap by {}", a.id, b.id, overlap);
+            }
+        }
+    }
+
+    #[test]
+    fn test_index_follows_moving_mass_food() {
+        let mut manager =
+            MassFoodManager::new(QuadTree::new(Rectangle::new(0.0, 0.0, 1_000.0, 1_000.0), 8));
+        manager.add_new(&point(100.0, 500.0), &point(1.0, 0.0), &point(100.0, 500.0), 0, 20);
+
+        while manager.data[0].speed.is_some() {
+            manager.move_food(1_000.0, 1_000.0, 0.5);
+        }
+
+        let mass_food = manager.data[0].clone();
+        assert!(mass_food.point.x > 200.0);
+        assert!(manager.retrieve(&Rectangle::around(&point(100.0, 500.0), 10.0)).is_empty());
+        let found = manager.retrieve(&Rectangle::around(&mass_food.point, 0.0));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, mass_food.id);
+    }
+
+    #[test]
+    fn test_big_piles_are_found_once() {
+        assert!(manager_with_pile(3).find_blobs(200.0, 100).is_empty());
+
+        let blobs = manager_with_pile(6).find_blobs(200.0, 100);
+        assert_eq!(blobs.len(), 1);
+        assert!(blobs[0].distance(&point(500.0, 500.0)) < 200.0);
+    }
+}
@@ -2,4 +2,6 @@ pub mod player_manager;
 pub mod food_manager;
 pub mod virus_manager;
 pub mod mass_food_manager;
-pub mod amount_manager;
\ No newline at end of file
+pub mod amount_manager;
+pub mod round_manager;
+pub mod power_up_manager;
\ No newline at end of file
@@ -104,9 +104,8 @@ impl PlayerManager {
         players_data
     }
 
-    pub async fn get_top_players(&self) -> Vec<LeaderboardPlayer> {
-        // First, clone the players to a mutable local variable to sort
-
+    // every player sorted by mass, biggest first
+    pub async fn get_standings(&self) -> Vec<LeaderboardPlayer> {
         let mut players = vec![];
         for player in self.players.values() {
             let player = player.read().await;
@@ -123,7 +122,7 @@ impl PlayerManager {
                 .unwrap_or(std::cmp::Ordering::Equal)
         });
 
-        players.into_iter().take(10).collect()
+        players
     }
 
     pub async fn get_total_mass(&self) -> TotalMass {
@@ -137,6 +136,10 @@ impl PlayerManager {
     pub async fn set_bet(&self, id: PlayerID, bet: u64) -> Option<()> {
         if let Some(player) = self.players.get(&id) {
             let mut player = player.write().await;
+            // bots never hold bets
+            if player.bot {
+                return None;
+            }
             // Perform modifications
             player.bet = bet;
             player.bet_set = true;
@@ -0,0 +1,109 @@
+use crate::{
+    map::{
+        cell::Cell,
+        point::{AsPoint, Point},
+        power_up::{PowerUp, PowerUpKind, POWER_UP_RADIUS},
+    },
+    utils::{id::PowerUpID, quad_tree::Rectangle, util::{are_colliding, random_in_range}},
+};
+
+// there are only a handful of power ups on the map, a plain list is enough
+pub struct PowerUpManager {
+    pub data: Vec<PowerUp>,
+    id_counter: PowerUpID,
+}
+
+impl PowerUpManager {
+    pub fn new() -> Self {
+        PowerUpManager {
+            data: Vec::new(),
+            id_counter: PowerUpID::MAX,
+        }
+    }
+
+    fn get_new_id(&mut self) -> PowerUpID {
+        self.id_counter = self.id_counter.wrapping_add(1);
+        self.id_counter
+    }
+
+    // new power ups are placed at random inside `area`
+    pub fn create_many(&mut self, amount: usize, area: &Rectangle) -> Vec<PowerUp> {
+        if area.w <= POWER_UP_RADIUS * 2.0 || area.h <= POWER_UP_RADIUS * 2.0 {
+            return vec![];
+        }
+
+        let mut new_power_ups = vec![];
+
+        for _ in 0..amount {
+            let position = Point {
+                x: random_in_range(area.x + POWER_UP_RADIUS..area.x + area.w - POWER_UP_RADIUS),
+                y: random_in_range(area.y + POWER_UP_RADIUS..area.y + area.h - POWER_UP_RADIUS),
+                radius: POWER_UP_RADIUS,
+            };
+            let power_up = PowerUp::new(self.get_new_id(), PowerUpKind::random(), &position);
+
+            new_power_ups.push(power_up.clone());
+            self.data.push(power_up);
+        }
+
+        new_power_ups
+    }
+
+    // removes and returns every power up touched by one of the cells
+    pub fn take_touched(&mut self, cells: &[Cell]) -> Vec<PowerUp> {
+        let mut touched = vec![];
+
+        self.data.retain(|power_up| {
+            let power_up_point = power_up.as_point();
+            if cells
+                .iter()
+                .any(|cell| are_colliding(&cell.position, &power_up_point))
+            {
+                touched.push(power_up.clone());
+                return false;
+            }
+            true
+        });
+
+        touched
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    pub fn count(&self) -> usize {
+        self.data.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        map::cell::Cell,
+        utils::quad_tree::Rectangle,
+    };
+
+    use super::PowerUpManager;
+
+    #[test]
+    fn test_touched_power_ups_are_taken_once() {
+        let mut manager = PowerUpManager::new();
+        let power_ups = manager.create_many(3, &Rectangle::new(0.0, 0.0, 500.0, 500.0));
+        assert_eq!(manager.count(), 3);
+
+        // too small to fit a power up
+        assert!(manager.create_many(3, &Rectangle::new(0.0, 0.0, 10.0, 10.0)).is_empty());
+
+        let target = &power_ups[1];
+        let cell = Cell::new(target.x, target.y, 10, 0.0, true, None, None);
+
+        let taken = manager.take_touched(std::slice::from_ref(&cell));
+        assert!(taken.iter().any(|power_up| power_up.id == target.id));
+        assert!(manager.take_touched(&[cell]).is_empty());
+        assert_eq!(manager.count(), 3 - taken.len());
+
+        let far_cell = Cell::new(10_000.0, 10_000.0, 10, 0.0, true, None, None);
+        assert!(manager.take_touched(&[far_cell]).is_empty());
+    }
+}
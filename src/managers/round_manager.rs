@@ -0,0 +1,87 @@
+pub enum RoundEvent {
+    Countdown(i64),
+    Ended,
+}
+
+pub struct RoundManager {
+    duration: Option<i64>,
+    countdown: i64,
+    pub round: u32,
+    pub round_start: i64,
+}
+
+impl RoundManager {
+    pub fn new(duration: Option<i64>, countdown: i64, now: i64) -> Self {
+        RoundManager {
+            duration,
+            countdown,
+            round: 1,
+            round_start: now,
+        }
+    }
+
+    pub fn get_round_end(&self) -> Option<i64> {
+        self.duration.map(|duration| self.round_start + duration)
+    }
+
+    // returns what has to be announced for the current round at `now`, if anything
+    pub fn check(&self, now: i64) -> Option<RoundEvent> {
+        let seconds_left = self.get_round_end()? - now;
+
+        if seconds_left <= 0 {
+            return Some(RoundEvent::Ended);
+        }
+
+        if seconds_left <= self.countdown {
+            return Some(RoundEvent::Countdown(seconds_left));
+        }
+
+        None
+    }
+
+    pub fn start_next(&mut self, now: i64) {
+        self.round = self.round.wrapping_add(1);
+        self.round_start = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RoundEvent, RoundManager};
+
+    #[test]
+    fn test_countdown_then_round_end() {
+        let manager = RoundManager::new(Some(60), 10, 1_000);
+        assert_eq!(manager.get_round_end(), Some(1_060));
+
+        // nothing to announce before the countdown window
+        assert!(manager.check(1_000).is_none());
+        assert!(manager.check(1_049).is_none());
+
+        assert!(matches!(manager.check(1_050), Some(RoundEvent::Countdown(10))));
+        assert!(matches!(manager.check(1_059), Some(RoundEvent::Countdown(1))));
+
+        assert!(matches!(manager.check(1_060), Some(RoundEvent::Ended)));
+        assert!(matches!(manager.check(1_100), Some(RoundEvent::Ended)));
+    }
+
+    #[test]
+    fn test_endless_rounds_never_end() {
+        let manager = RoundManager::new(None, 10, 1_000);
+        assert_eq!(manager.get_round_end(), None);
+        assert!(manager.check(i64::MAX).is_none());
+    }
+
+    #[test]
+    fn test_start_next_resets_round_end() {
+        let mut manager = RoundManager::new(Some(60), 10, 1_000);
+        assert!(matches!(manager.check(1_060), Some(RoundEvent::Ended)));
+
+        manager.start_next(1_060);
+        assert_eq!(manager.round, 2);
+        assert_eq!(manager.round_start, 1_060);
+        assert_eq!(manager.get_round_end(), Some(1_120));
+        assert!(manager.check(1_060).is_none());
+        assert!(matches!(manager.check(1_115), Some(RoundEvent::Countdown(5))));
+    }
+}
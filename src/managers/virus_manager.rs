@@ -1,27 +1,29 @@
 use crate::{
-    config::VirusConfig,
+    config::get_current_config,
     map::{
+        arena::VirusSpot,
         point::Point,
         virus::{Virus, VirusData},
     },
     utils::{
         consts::Mass,
         id::VirusID,
-        util::{create_random_position, mass_to_radius, random_in_range},
+        quad_tree::{QuadTree, Rectangle},
+        util::{are_colliding, create_random_position, mass_to_radius, random_in_range},
     },
 };
 
 pub struct VirusManager {
     pub data: Vec<Virus>,
-    virus_config: VirusConfig,
+    quad_tree: QuadTree<Virus>,
     id_counter: VirusID,
 }
 
 impl VirusManager {
-    pub fn new() -> Self {
+    pub fn new(quad_tree: QuadTree<Virus>) -> Self {
         VirusManager {
             data: Vec::new(),
-            virus_config: VirusConfig::default(), // Correctly assign the `virus` field from `config`
+            quad_tree,
             id_counter: VirusID::MAX,
         }
     }
@@ -37,25 +39,77 @@ impl VirusManager {
         Virus::new(virus_id, position, mass, direction)
     }
 
+    // only for restore and clear, a virus updated in the game tick is moved with reindex
+    fn rebuild_index(&mut self) {
+        self.quad_tree.clear();
+
+        for virus in self.data.iter() {
+            self.quad_tree.insert(virus.clone());
+        }
+    }
+
+    // moves the entry of the virus at `index` to where it is now
+    pub fn reindex(&mut self, index: usize) {
+        let virus = &self.data[index];
+        self.quad_tree.remove_by_id(virus.id);
+        self.quad_tree.insert(virus.clone());
+    }
+
     pub fn push_new(&mut self, virus: Virus) {
+        self.quad_tree.insert(virus.clone());
         self.data.push(virus);
     }
 
+    // with virus spots on the map, viruses only appear on the free spots
     pub fn create_many_virus(&mut self, number: usize) -> Vec<VirusData>{
+        let config = get_current_config();
+        let virus_spots = &config.arena.virus_spots;
+        if !virus_spots.is_empty() {
+            return self.create_on_spots(number, virus_spots);
+        }
+
+        let virus_config = &config.virus;
         let mut new_virus_data = vec![];
 
         for _ in 0..number {
-            let mass = random_in_range(
-                self.virus_config.default_mass.from..self.virus_config.default_mass.to,
-            );
+            let mass =
+                random_in_range(virus_config.default_mass.from..virus_config.default_mass.to);
             let radius = mass_to_radius(mass);
-            let position =
-                create_random_position(self.virus_config.uniform_disposition, radius, None);
+            let position = create_random_position(virus_config.uniform_disposition, radius, None);
             let new_virus = self.create_virus(position, mass, None);
 
             new_virus_data.push(new_virus.generate_data());
 
-            self.data.push(new_virus);
+            self.push_new(new_virus);
+        }
+
+        new_virus_data
+    }
+
+    fn create_on_spots(&mut self, number: usize, virus_spots: &[VirusSpot]) -> Vec<VirusData> {
+        let virus_config = &get_current_config().virus;
+        let mut new_virus_data = vec![];
+
+        for spot in virus_spots {
+            if new_virus_data.len() >= number {
+                break;
+            }
+
+            let mass =
+                random_in_range(virus_config.default_mass.from..virus_config.default_mass.to);
+            let position = Point {
+                x: spot.x,
+                y: spot.y,
+                radius: mass_to_radius(mass),
+            };
+
+            if self.data.iter().any(|virus| are_colliding(&virus.get_position(), &position)) {
+                continue;
+            }
+
+            let new_virus = self.create_virus(position, mass, None);
+            new_virus_data.push(new_virus.generate_data());
+            self.push_new(new_virus);
         }
 
         new_virus_data
@@ -64,12 +118,12 @@ impl VirusManager {
     //Divides a virus by reducing its mass and creating a new virus with the initial position being the center of the original virus,
     //and the new direction being the last direction aimed by the player right before the split
     pub fn shoot_one(&mut self, position: Point, direction: Point) -> VirusData {
-        let mass =
-            random_in_range(self.virus_config.default_mass.from..self.virus_config.default_mass.to);
+        let virus_config = &get_current_config().virus;
+        let mass = random_in_range(virus_config.default_mass.from..virus_config.default_mass.to);
 
         let mut new_virus = self.create_virus(position, mass, Some(direction));
 
-        new_virus.set_speed(35.0);
+        new_virus.set_speed(virus_config.shoot_speed);
 
         let virus_data = new_virus.generate_data();
 
@@ -82,11 +136,32 @@ impl VirusManager {
         match self.data.iter().position(|x| x.id == virus_id) {
             Some(index) => {
                 self.data.remove(index);
+                self.quad_tree.remove_by_id(virus_id);
             }
             None => {}
         }
     }
 
+    // every virus touching the range
+    pub fn retrieve(&self, range: &Rectangle) -> Vec<Virus> {
+        let mut found = vec![];
+        self.quad_tree.retrieve(range, &mut found);
+        found
+    }
+
+    pub fn restore(&mut self, viruses: Vec<Virus>) {
+        if let Some(max_id) = viruses.iter().map(|v| v.id).max() {
+            self.id_counter = max_id;
+        }
+        self.data = viruses;
+        self.rebuild_index();
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.rebuild_index();
+    }
+
     pub fn count(&self) -> usize {
         self.data.len()
     }
@@ -0,0 +1,370 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::point::Point;
+use crate::utils::quad_tree::Rectangle;
+
+// tries before giving up on finding a spawn point outside of the obstacles
+const SPAWN_ATTEMPTS: usize = 10;
+// food ids are built from the u16 coordinates of the food
+const MAX_MAP_SIZE: u32 = u16::MAX as u32;
+
+// static shapes the cells collide with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Obstacle {
+    Circle { x: f32, y: f32, radius: f32 },
+    Polygon { points: Vec<[f32; 2]> }, // in order, clockwise or not
+}
+
+impl Obstacle {
+    // moves the circle until it only touches the obstacle, returns true if it was overlapping
+    pub fn push_out(&self, circle: &mut Point) -> bool {
+        match self {
+            Obstacle::Circle { x, y, radius } => {
+                let (dx, dy) = (circle.x - x, circle.y - y);
+                let distance = dx.hypot(dy);
+                let min_distance = radius + circle.radius;
+
+                if distance >= min_distance {
+                    return false;
+                }
+
+                let (nx, ny) = if distance > 0.0 {
+                    (dx / distance, dy / distance)
+                } else {
+                    (1.0, 0.0)
+                };
+                circle.x = x + nx * min_distance;
+                circle.y = y + ny * min_distance;
+                true
+            }
+            Obstacle::Polygon { points } => {
+                if points.len() < 3 {
+                    return false;
+                }
+
+                let inside = polygon_contains(points, circle);
+                let closest = closest_on_polygon(points, circle);
+                let distance = circle.distance(&closest);
+
+                if !inside && distance >= circle.radius {
+                    return false;
+                }
+
+                // from the edge toward the center, flipped when the center is inside
+                let (mut nx, mut ny) = if distance > 0.0 {
+                    ((circle.x - closest.x) / distance, (circle.y - closest.y) / distance)
+                } else {
+                    (1.0, 0.0)
+                };
+                if inside {
+                    nx = -nx;
+                    ny = -ny;
+                }
+
+                circle.x = closest.x + nx * circle.radius;
+                circle.y = closest.y + ny * circle.radius;
+                true
+            }
+        }
+    }
+
+    pub fn overlaps(&self, circle: &Point) -> bool {
+        self.push_out(&mut circle.clone())
+    }
+}
+
+// even-odd rule
+fn polygon_contains(points: &[[f32; 2]], point: &Point) -> bool {
+    let mut inside = false;
+    let mut j = points.len() - 1;
+
+    for i in 0..points.len() {
+        let ([xi, yi], [xj, yj]) = (points[i], points[j]);
+        if (yi > point.y) != (yj > point.y)
+            && point.x < (xj - xi) * (point.y - yi) / (yj - yi) + xi
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+
+    inside
+}
+
+fn closest_on_polygon(points: &[[f32; 2]], point: &Point) -> Point {
+    let mut closest = Point {
+        x: points[0][0],
+        y: points[0][1],
+        radius: 0.0,
+    };
+    let mut closest_distance = f32::INFINITY;
+
+    for i in 0..points.len() {
+        let [ax, ay] = points[i];
+        let [bx, by] = points[(i + 1) % points.len()];
+        let (ex, ey) = (bx - ax, by - ay);
+        let length_pow = ex * ex + ey * ey;
+
+        let t = if length_pow > 0.0 {
+            (((point.x - ax) * ex + (point.y - ay) * ey) / length_pow).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let candidate = Point {
+            x: ax + ex * t,
+            y: ay + ey * t,
+            radius: 0.0,
+        };
+
+        let distance = point.distance_pow(&candidate);
+        if distance < closest_distance {
+            closest = candidate;
+            closest_distance = distance;
+        }
+    }
+
+    closest
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirusSpot {
+    pub x: f32,
+    pub y: f32,
+}
+
+// food spawns in the regions in proportion of density * area
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoodRegion {
+    #[serde(flatten)]
+    pub area: Rectangle,
+    pub density: f32,
+}
+
+// how new food is placed inside the spawn area
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FoodDistribution {
+    #[default]
+    Uniform,
+    // evenly spaced, a food is never placed closer than min_distance to another one
+    PoissonDisk { min_distance: f32 },
+    // feeding grounds, food gathers around a few fixed spots
+    Clustered { grounds: usize, spread: f32 },
+    // keeps out of the reach of the players cells
+    AvoidPlayers { distance: f32 },
+}
+
+// An arena loaded from a map file (MAP_FILE, json), empty lists keep the default behavior:
+// no obstacles, random viruses, food and spawn points over the whole map, borders that block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArenaMap {
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+    #[serde(default)]
+    pub virus_spots: Vec<VirusSpot>,
+    #[serde(default)]
+    pub food_regions: Vec<FoodRegion>,
+    #[serde(default)]
+    pub food_distribution: FoodDistribution,
+    #[serde(default)]
+    pub spawn_zones: Vec<Rectangle>,
+    // toroidal arena, what leaves by one edge comes back by the opposite one
+    #[serde(default)]
+    pub wrap: bool,
+}
+
+impl ArenaMap {
+    pub fn plain(width: u32, height: u32) -> Self {
+        ArenaMap {
+            width,
+            height,
+            obstacles: vec![],
+            virus_spots: vec![],
+            food_regions: vec![],
+            food_distribution: FoodDistribution::Uniform,
+            spawn_zones: vec![],
+            wrap: false,
+        }
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<ArenaMap> {
+        let arena: ArenaMap = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        arena.validate()?;
+        Ok(arena)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            anyhow::bail!("the map has no size");
+        }
+        if self.width > MAX_MAP_SIZE || self.height > MAX_MAP_SIZE {
+            anyhow::bail!(
+                "the map is {}x{}, the biggest supported is {}x{}",
+                self.width,
+                self.height,
+                MAX_MAP_SIZE,
+                MAX_MAP_SIZE
+            );
+        }
+        Ok(())
+    }
+
+    pub fn wrap_size(&self) -> Option<(f32, f32)> {
+        self.wrap.then_some((self.width as f32, self.height as f32))
+    }
+
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle::new(0.0, 0.0, self.width as f32, self.height as f32)
+    }
+
+    // returns true if the circle was moved
+    pub fn push_out_of_obstacles(&self, circle: &mut Point) -> bool {
+        let mut pushed = false;
+        for obstacle in self.obstacles.iter() {
+            pushed |= obstacle.push_out(circle);
+        }
+        pushed
+    }
+
+    pub fn is_blocked(&self, circle: &Point) -> bool {
+        self.obstacles.iter().any(|obstacle| obstacle.overlaps(circle))
+    }
+
+    // a random point in one of the spawn zones, away from the obstacles when possible
+    pub fn random_spawn_point(&self, radius: f32) -> Point {
+        let mut rng = rand::thread_rng();
+        let mut point = Point {
+            x: 0.0,
+            y: 0.0,
+            radius,
+        };
+
+        for _ in 0..SPAWN_ATTEMPTS {
+            let zone = if self.spawn_zones.is_empty() {
+                self.bounds()
+            } else {
+                self.spawn_zones[rng.gen_range(0..self.spawn_zones.len())]
+            };
+
+            point = random_point_in(&zone, radius);
+            if !self.is_blocked(&point) {
+                break;
+            }
+        }
+
+        point
+    }
+
+    // index of a food region, picked in proportion of its density * area
+    pub fn pick_food_region(&self, rng: &mut impl Rng) -> Option<usize> {
+        let weight = |region: &FoodRegion| (region.density * region.area.w * region.area.h).max(0.0);
+        let total: f32 = self.food_regions.iter().map(weight).sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut picked = rng.gen_range(0.0..total);
+        for (index, region) in self.food_regions.iter().enumerate() {
+            picked -= weight(region);
+            if picked < 0.0 {
+                return Some(index);
+            }
+        }
+
+        Some(self.food_regions.len() - 1)
+    }
+}
+
+// the center of the area when it's too small for the circle
+fn random_point_in(area: &Rectangle, radius: f32) -> Point {
+    let mut rng = rand::thread_rng();
+    let mut random_in = |from: f32, size: f32| {
+        if size > radius * 2.0 {
+            rng.gen_range(from + radius..from + size - radius)
+        } else {
+            from + size / 2.0
+        }
+    };
+
+    let x = random_in(area.x, area.w);
+    Point {
+        x,
+        y: random_in(area.y, area.h),
+        radius,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::point::Point;
+
+    use super::{ArenaMap, FoodDistribution, Obstacle};
+
+    fn circle(x: f32, y: f32, radius: f32) -> Point {
+        Point { x, y, radius }
+    }
+
+    #[test]
+    fn test_cells_are_pushed_out_of_obstacles() {
+        let rock = Obstacle::Circle {
+            x: 100.0,
+            y: 100.0,
+            radius: 50.0,
+        };
+        let mut cell = circle(130.0, 100.0, 10.0);
+        assert!(rock.push_out(&mut cell));
+        assert_eq!((cell.x, cell.y), (160.0, 100.0));
+        assert!(!rock.push_out(&mut cell));
+
+        let wall = Obstacle::Polygon {
+            points: vec![[0.0, 0.0], [100.0, 0.0], [100.0, 20.0], [0.0, 20.0]],
+        };
+        // center inside the wall, close to its bottom edge
+        let mut cell = circle(50.0, 15.0, 10.0);
+        assert!(wall.push_out(&mut cell));
+        assert_eq!((cell.x, cell.y), (50.0, 30.0));
+
+        // only touching the edge from outside
+        let mut cell = circle(50.0, -5.0, 10.0);
+        assert!(wall.push_out(&mut cell));
+        assert_eq!((cell.x, cell.y), (50.0, -10.0));
+
+        assert!(!wall.overlaps(&circle(150.0, 10.0, 10.0)));
+    }
+
+    #[test]
+    fn test_map_file_format() {
+        let arena: ArenaMap = serde_json::from_str(
+            r#"{
+                "width": 4000,
+                "height": 3000,
+                "obstacles": [
+                    { "type": "circle", "x": 2000, "y": 1500, "radius": 300 },
+                    { "type": "polygon", "points": [[0, 0], [500, 0], [0, 500]] }
+                ],
+                "food_regions": [{ "x": 0, "y": 0, "w": 1000, "h": 1000, "density": 2.0 }],
+                "spawn_zones": [{ "x": 3000, "y": 2000, "w": 1000, "h": 1000 }]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(arena.validate().is_ok());
+        assert!(ArenaMap::plain(70_000, 3_000).validate().is_err());
+        assert!(ArenaMap::plain(4_000, 0).validate().is_err());
+
+        assert_eq!(arena.obstacles.len(), 2);
+        assert!(arena.virus_spots.is_empty());
+        assert_eq!(arena.pick_food_region(&mut rand::thread_rng()), Some(0));
+        assert!(matches!(arena.food_distribution, FoodDistribution::Uniform));
+
+        for _ in 0..20 {
+            let point = arena.random_spawn_point(10.0);
+            assert!(point.x >= 3_010.0 && point.y >= 2_010.0);
+            assert!(!arena.is_blocked(&point));
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::utils::{
-    consts::{Mass, MIN_DISTANCE, MIN_SPEED, SPLIT_CELL_SPEED},
+    consts::{Mass, MIN_DISTANCE, MIN_SPEED, PUSHING_AWAY_SPEED, SPLIT_CELL_SPEED},
     util::{lerp_deg, lerp_move, mass_to_radius, math_log, get_current_timestamp},
 };
 
@@ -65,6 +65,11 @@ impl Cell {
         }
     }
 
+    // thrown by a split, it doesn't steer until it slows down
+    pub fn is_flying(&self) -> bool {
+        !self.can_move
+    }
+
     pub fn mark_for_removal(&mut self) {
         self.to_be_removed = true;
     }
@@ -82,10 +87,32 @@ impl Cell {
         self.set_mass(self.mass.saturating_add(to_add));
     }
 
+    // moves both cells a step away from each other
+    pub fn push_away(&mut self, other: &mut Cell) {
+        let (dx, dy) = self.position.delta_to(&other.position);
+        let vector = Point {
+            x: dx + 20.0,
+            y: dy + 20.0,
+            radius: 0.0,
+        }
+        .normalize()
+        .scale(PUSHING_AWAY_SPEED);
+
+        self.position.x -= vector.x;
+        self.position.y -= vector.y;
+        other.position.x += vector.x;
+        other.position.y += vector.y;
+    }
+
     fn recalculate_radius(&mut self) {
         self.position.radius = mass_to_radius(self.mass);
     }
 
+    pub fn teleport_to(&mut self, position: &Point) {
+        self.position.x = position.x;
+        self.position.y = position.y;
+    }
+
     pub fn move_cell(
         &mut self,
         player_position: &Point,
@@ -93,16 +120,11 @@ impl Cell {
         mouse_y: f32,
         slow_base: f32,
         init_mass_log: f32,
-        is_teleport: bool
+        speed_factor: f32,
     ) {
-        if is_teleport{
-            self.position.x = player_position.x;
-            self.position.y = player_position.y;
-            return
-        }
-
-        let target_x = player_position.x - self.position.x + mouse_x;
-        let target_y = player_position.y - self.position.y + mouse_y;
+        let (to_player_x, to_player_y) = self.position.delta_to(player_position);
+        let target_x = to_player_x + mouse_x;
+        let target_y = to_player_y + mouse_y;
         let dist = (target_y.powi(2) + target_x.powi(2)).sqrt();
         let deg = target_y.atan2(target_x);
 
@@ -144,8 +166,8 @@ impl Cell {
                 delta_x = 0.0;
             }
         }
-        self.position.y += delta_y;
-        self.position.x += delta_x;
+        self.position.y += delta_y * speed_factor;
+        self.position.x += delta_x * speed_factor;
         // info!("speed: {}", self.speed);
     }
 }
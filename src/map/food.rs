@@ -1,8 +1,10 @@
 use super::point::{AsPoint, Point};
 use crate::utils::consts::Mass;
 use crate::utils::id::FoodID;
+use crate::utils::quad_tree::Indexable;
 use crate::utils::util::mass_to_radius;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 // #[derive(Serialize)]
 // pub struct FoodData {
@@ -15,7 +17,7 @@ use rand::Rng;
 
 pub type FoodData = (FoodID, u16);
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Food {
     pub id: FoodID,
     pub x: f32,
@@ -26,8 +28,8 @@ pub struct Food {
 }
 
 impl Food {
-    pub fn new(food_id: FoodID, point: &Point) -> Self {
-        let mut rng = rand::thread_rng();
+    // the rng comes from the caller, a seeded one keeps the whole food spawn reproducible
+    pub fn new(food_id: FoodID, point: &Point, rng: &mut impl Rng) -> Self {
         let mass = rng.gen_range(2..3);
         Food {
             id: food_id,
@@ -53,3 +55,11 @@ impl AsPoint for Food {
         }
     }
 }
+
+impl Indexable for Food {
+    type ID = FoodID;
+
+    fn get_id(&self) -> FoodID {
+        self.id
+    }
+}
@@ -1,15 +1,19 @@
-use super::point::Point;
+use super::point::{AsPoint, Point};
+use crate::config::MassFoodConfig;
 use crate::utils::consts::Mass;
-use crate::utils::game_logic::adjust_for_boundaries;
+use crate::utils::game_logic::keep_in_arena;
 use crate::utils::id::MassFoodID;
-use crate::utils::util::{are_colliding, mass_to_radius};
-use serde::Serialize;
+use crate::utils::quad_tree::Indexable;
+use crate::utils::rules::EatRule;
+use crate::utils::util::{get_current_timestamp, mass_to_radius};
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Clone)]
 pub struct MassFoodUpdateData {
     pub id: MassFoodID,
     pub x: f32,
     pub y: f32,
+    pub radius: f32,
 }
 
 #[derive(Serialize, Clone)]
@@ -21,7 +25,7 @@ pub struct MassFoodInitData {
     pub y: f32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MassFood {
     pub id: MassFoodID,
     pub mass: Mass,
@@ -29,6 +33,8 @@ pub struct MassFood {
     pub direction: Point,
     pub point: Point,
     pub speed: Option<f32>,
+    #[serde(default)]
+    pub created_at: i64,
 }
 
 impl MassFood {
@@ -40,9 +46,10 @@ impl MassFood {
         cell_transform: &Point,
         mass: Mass,
     ) -> Self {
+        let (to_player_x, to_player_y) = cell_transform.delta_to(position);
         let direction = Point {
-            x: 0.4 * (position.x - cell_transform.x) + direction.x,
-            y: 0.4 * (position.y - cell_transform.y) + direction.y,
+            x: 0.4 * to_player_x + direction.x,
+            y: 0.4 * to_player_y + direction.y,
             radius: 0.0, // Radius doesn't participate in direction calculation
         }
         .normalize();
@@ -58,6 +65,7 @@ impl MassFood {
                 radius: mass_to_radius(mass),
             },
             speed: Some(30.0),
+            created_at: get_current_timestamp(),
         }
     }
 
@@ -76,18 +84,35 @@ impl MassFood {
             id: self.id,
             x: self.point.x,
             y: self.point.y,
+            radius: self.point.radius,
         }
     }
-    
-    pub fn can_be_eat_by(&self, cell_mass: Mass, cell_position: &Point) -> bool {
-        if are_colliding(&cell_position, &self.point) {
-            if self.speed.unwrap_or_default() > 0.0 {
-                return false;
-            }
-            return (cell_mass as f32) > ((self.mass as f32) * 1.1f32)
-                && self.speed.unwrap_or_default() < 25.0;
+
+    // loses mass steadily after decay_start, so it's gone at the end of its lifetime.
+    // meant to run once a second, returns false once the mass food expired
+    pub fn decay(&mut self, now: i64, config: &MassFoodConfig) -> bool {
+        let age = now - self.created_at;
+        let seconds_left = config.lifetime - age;
+
+        if seconds_left <= 0 {
+            return false;
+        }
+
+        if age > config.decay_start {
+            let loss = self.mass.div_ceil((seconds_left + 1).min(Mass::MAX as i64) as Mass);
+            self.mass = self.mass.saturating_sub(loss);
+            self.point.radius = mass_to_radius(self.mass);
+        }
+
+        self.mass > 0
+    }
+
+    pub fn can_be_eat_by(&self, rule: &EatRule, cell_mass: Mass, cell_position: &Point) -> bool {
+        // mass food can't be eaten while it's still flying
+        if self.speed.unwrap_or_default() > 0.0 {
+            return false;
         }
-        false
+        rule.can_eat(cell_mass, cell_position, self.mass, &self.point)
     }
 
     pub fn move_self(&mut self, game_width: f32, game_height: f32) {
@@ -102,7 +127,7 @@ impl MassFood {
             self.point.x += delta_x;
             self.point.y += delta_y;
 
-            adjust_for_boundaries(
+            keep_in_arena(
                 &mut self.point.x,
                 &mut self.point.y,
                 self.point.radius,
@@ -113,3 +138,17 @@ impl MassFood {
         }
     }
 }
+
+impl AsPoint for MassFood {
+    fn as_point(&self) -> Point {
+        self.point
+    }
+}
+
+impl Indexable for MassFood {
+    type ID = MassFoodID;
+
+    fn get_id(&self) -> MassFoodID {
+        self.id
+    }
+}
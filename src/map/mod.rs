@@ -1,6 +1,8 @@
+pub mod arena;
 pub mod player;
 pub mod point;
 pub mod food;
 pub mod virus;
 pub mod mass_food;
-pub mod cell;
\ No newline at end of file
+pub mod cell;
+pub mod power_up;
\ No newline at end of file
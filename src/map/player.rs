@@ -1,20 +1,25 @@
 use std::sync::atomic::AtomicBool;
+use std::f32::consts::TAU;
 use std::sync::Arc;
 
 use super::cell::Cell;
 use super::point::Point;
-use crate::config::get_current_config;
+use super::power_up::{ActiveEffect, PowerUpKind};
+use crate::config::{get_current_config, VirusPopConfig};
+use crate::snapshot::{CellSnapshot, PlayerSnapshot};
 use crate::utils::consts::{
-    Mass, TotalMass, MERGE_TIMER, MIN_SPEED, PUSHING_AWAY_SPEED, SPLIT_CELL_SPEED,
+    Mass, TotalMass, MAX_SPLITS_PER_INPUT, MERGE_TIMER, MIN_SPEED, SPLIT_CELL_SPEED,
 };
-use crate::utils::game_logic::adjust_for_boundaries;
+use crate::utils::game_logic::keep_in_arena;
 use crate::utils::id::PlayerID;
 use crate::utils::quad_tree::Rectangle;
+use crate::utils::rules::check_overlap;
 use crate::utils::util::{
-    check_overlap, create_random_position_in_range, get_current_timestamp, lerp, mass_to_radius,
+    create_random_position_in_range, get_current_timestamp, lerp, mass_to_radius,
     total_mass_to_radius,
 };
 use log::{debug, info};
+use rand::{seq::SliceRandom, Rng};
 use serde::{Deserialize, Serialize};
 use socketioxide::socket::Sid;
 use tokio::sync::{Mutex, Semaphore};
@@ -28,6 +33,10 @@ pub struct PlayerUpdateData {
     pub bet: u64,
     pub won: u64,
     pub can_teleport: bool,
+    pub teleport_charges: u8,
+    pub effects: Vec<ActiveEffect>,
+    pub frozen: bool,
+    pub protected_until: i64,
 }
 
 #[derive(Serialize, Clone, Deserialize)]
@@ -37,6 +46,8 @@ pub struct PlayerInitData {
     pub hue: u16,
     pub img_url: Option<String>,
     pub name: Option<String>,
+    pub team: Option<u8>,
+    pub bot: bool,
 }
 
 #[derive(Clone)]
@@ -60,8 +71,19 @@ pub struct Player {
     pub bet: u64,
     pub bet_set: bool,
     pub total_won: u64,
-    pub can_teleport: bool,
-    pub cashout_control: Arc<Mutex<bool>> // only one cash out at time
+    pub teleport_charges: u8,
+    pub last_teleport: i64,    // in seconds
+    pub recharge_started: i64, // when the charge being refilled started, in seconds
+    pub cashout_control: Arc<Mutex<bool>>, // only one cash out at time
+    pub restored: bool, // cells come from a snapshot, the next respawn keeps them
+    pub team: Option<u8>, // set by the teams mode, the hue is then the team color
+    pub bot: bool,        // driven by the server, without connection nor bet
+    pub effects: Vec<ActiveEffect>,
+    pub feeding: bool,        // the feed input is held, mass is ejected at the feed rate
+    pub feed_requested: bool, // a single feed, ejected on the next tick
+    pub last_feed: i64,       // in microseconds
+    pub frozen: bool,         // the cells stop steering toward the target
+    pub protected_until: i64, // spawn protection, in seconds
 }
 
 impl Player {
@@ -86,8 +108,19 @@ impl Player {
             bet: 0,
             bet_set: false,
             total_won: 0,
-            can_teleport: true,
-            cashout_control: Arc::new(Mutex::new(false)) // if locked, is cashing out
+            teleport_charges: get_current_config().teleport.max_charges,
+            last_teleport: 0,
+            recharge_started: 0,
+            cashout_control: Arc::new(Mutex::new(false)), // if locked, is cashing out
+            restored: false,
+            team: None,
+            bot: false,
+            effects: Vec::new(),
+            feeding: false,
+            feed_requested: false,
+            last_feed: 0,
+            frozen: false,
+            protected_until: 0,
         }
     }
 
@@ -96,6 +129,9 @@ impl Player {
         self.y = new_position.y;
         self.target_x = 0.0;
         self.target_y = 0.0;
+        self.frozen = false;
+        self.protected_until = get_current_timestamp() + get_current_config().spawn_protection;
+        self.teleport_charges = get_current_config().teleport.max_charges;
 
         self.cells = vec![Cell::new(
             new_position.x,
@@ -111,6 +147,40 @@ impl Player {
         self.recalculate_ratio();
     }
 
+    pub fn is_protected(&self, now: i64) -> bool {
+        now < self.protected_until
+    }
+
+    // splitting or ejecting gives up the spawn protection
+    pub fn end_protection(&mut self) {
+        self.protected_until = 0;
+    }
+
+    // picking the same power up again only extends it
+    pub fn add_effect(&mut self, kind: PowerUpKind, until: i64) {
+        match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => effect.until = effect.until.max(until),
+            None => self.effects.push(ActiveEffect { kind, until }),
+        }
+    }
+
+    pub fn has_effect(&self, kind: PowerUpKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    // drops the expired effects, cells can merge right away while merge reset is active
+    pub fn update_effects(&mut self, now: i64) {
+        self.effects.retain(|effect| effect.until > now);
+
+        if self.has_effect(PowerUpKind::MergeReset) {
+            for cell in self.cells.iter_mut() {
+                if let Some(ref mut time_to_merge) = cell.time_to_merge {
+                    *time_to_merge = (*time_to_merge).min(now - 1);
+                }
+            }
+        }
+    }
+
     pub fn setup(&mut self, name: Option<String>, img_url: Option<String>) {
         self.name = name;
         self.img_url = img_url.clone();
@@ -161,6 +231,8 @@ impl Player {
             name: self.name.clone(),
             hue: self.hue,
             img_url: self.img_url.clone(),
+            team: self.team,
+            bot: self.bot,
         }
     }
 
@@ -172,10 +244,73 @@ impl Player {
             y: self.y,
             bet: self.bet,
             won: self.total_won,
-            can_teleport: self.can_teleport,
+            can_teleport: self.teleport_charges > 0,
+            teleport_charges: self.teleport_charges,
+            effects: self.effects.clone(),
+            frozen: self.frozen,
+            protected_until: self.protected_until,
+        }
+    }
+
+    pub fn generate_snapshot(&self, user_id: i64) -> PlayerSnapshot {
+        PlayerSnapshot {
+            user_id,
+            name: self.name.clone(),
+            img_url: self.img_url.clone(),
+            hue: self.hue,
+            cells: self
+                .cells
+                .iter()
+                .map(|cell| CellSnapshot {
+                    x: cell.position.x,
+                    y: cell.position.y,
+                    mass: cell.mass,
+                    time_to_merge: cell.time_to_merge,
+                })
+                .collect(),
+            bet: self.bet,
+            total_won: self.total_won,
+            teleport_charges: self.teleport_charges,
         }
     }
 
+    pub fn restore_snapshot(&mut self, snapshot: PlayerSnapshot) {
+        // a team color always wins over the saved one
+        if self.team.is_none() {
+            self.hue = snapshot.hue;
+        }
+        self.bet = snapshot.bet;
+        self.bet_set = true;
+        self.total_won = snapshot.total_won;
+        self.teleport_charges = snapshot.teleport_charges;
+
+        self.cells = snapshot
+            .cells
+            .into_iter()
+            .map(|cell| {
+                Cell::new(
+                    cell.x,
+                    cell.y,
+                    cell.mass,
+                    MIN_SPEED,
+                    true,
+                    None,
+                    cell.time_to_merge,
+                )
+            })
+            .collect();
+
+        if !self.cells.is_empty() {
+            let cells_len = self.cells.len() as f32;
+            self.x = self.cells.iter().map(|c| c.position.x).sum::<f32>() / cells_len;
+            self.y = self.cells.iter().map(|c| c.position.y).sum::<f32>() / cells_len;
+            self.restored = true;
+        }
+
+        self.recalculate_total_mass();
+        self.recalculate_ratio();
+    }
+
     pub fn get_visible_area(&self) -> Rectangle {
         let half_width = (self.screen_width / self.ratio) / 2.0;
         let half_height = (self.screen_height / self.ratio) / 2.0;
@@ -221,7 +356,7 @@ impl Player {
 
     pub fn reduce_cell_mass(&mut self, cell_index: u8, mass: Mass) {
         self.cells[cell_index as usize].remove_mass(mass);
-        self.total_mass = self.total_mass.saturating_add(mass as usize);
+        self.total_mass = self.total_mass.saturating_sub(mass as TotalMass);
     }
 
     fn split_cell(
@@ -229,7 +364,7 @@ impl Player {
         cell_index: usize,
         max_requested_pieces: u8,
         default_player_mass: Mass,
-        split_dir: Option<f32>,
+        direction: Point,
     ) {
         if cell_index >= self.cells.len() {
             return; // Early return if the cell index is out of bounds
@@ -241,7 +376,7 @@ impl Player {
             (cell.position.x, cell.position.y, cell.mass)
         };
 
-        let max_allowed_pieces = (cell_mass / default_player_mass) as u8;
+        let max_allowed_pieces = (cell_mass / default_player_mass).min(u8::MAX as Mass) as u8;
         let pieces_to_create = max_requested_pieces.min(max_allowed_pieces);
 
         // info!(
@@ -253,27 +388,14 @@ impl Player {
             return;
         }
 
-        let new_cells_mass = cell_mass / (pieces_to_create.saturating_add(1) as Mass);
-        let angle_increment = 1.6 * std::f32::consts::PI / pieces_to_create as f32;
-
-        let mut directions = Vec::new();
+        let new_cells_mass = cell_mass / (pieces_to_create as Mass + 1);
+        // the rounding leftover stays in the splitting cell
+        let kept_mass = cell_mass - new_cells_mass * pieces_to_create as Mass;
 
-        if let Some(angle_base) = split_dir {
-            for i in 0..pieces_to_create {
-                let angle = angle_base + angle_increment * i as f32;
-                directions.push(Point {
-                    x: angle.cos(),
-                    y: angle.sin(),
-                    radius: 0.0,
-                });
-            }
-        } else {
-            let target_direction = self.calculate_target_direction(); // A method to calculate and normalize the target direction
-            directions.resize(pieces_to_create as usize, target_direction);
-        }
+        let directions = vec![direction; pieces_to_create as usize];
         let merge_duration = MERGE_TIMER + (self.total_mass as f32) / 100.0;
         // Update the original cell mass before creating new cells
-        self.cells[cell_index].set_mass(new_cells_mass);
+        self.cells[cell_index].set_mass(kept_mass);
         // Set time to merge for split cell
         self.cells[cell_index].time_to_merge =
             Some(get_current_timestamp() + merge_duration as i64);
@@ -296,52 +418,6 @@ impl Player {
         self.recalculate_ratio();
     }
 
-    // pub fn split_random(
-    //     &mut self,
-    //     cell_index: usize,
-    //     max_requested_pieces: u8,
-    //     default_player_mass: Mass,
-    // ) {
-    //     if cell_index >= self.cells.len() {
-    //         return; // Early return if the cell index is out of bounds
-    //     }
-
-    //     let cell_mass = self.cells[cell_index].mass;
-    //     if cell_mass < default_player_mass {
-    //         return; // Cannot split cells smaller than the minimum cell mass.
-    //     }
-
-    //     let max_allowed_pieces = (cell_mass / default_player_mass) as u8;
-    //     let pieces_to_create: usize = max_requested_pieces.min(max_allowed_pieces).into();
-    //     if pieces_to_create <= 1 {
-    //         return; // Not enough mass to split into more than one piece
-    //     }
-
-    //     let masses =
-    //         self.distribute_mass_randomly(cell_mass, pieces_to_create, default_player_mass);
-
-    //     let cell_position = (
-    //         self.cells[cell_index].position.x,
-    //         self.cells[cell_index].position.y,
-    //     );
-    //     // Update the original cell's mass to the last piece's mass before creating new cells
-    //     self.cells[cell_index].set_mass(masses[pieces_to_create as usize - 1]);
-
-    //     // Create new cells with the distributed masses
-    //     for &mass in &masses[..pieces_to_create as usize - 1] {
-    //         self.cells.push(Cell::new(
-    //             cell_position.0,
-    //             cell_position.1,
-    //             mass,
-    //             SPLIT_CELL_SPEED,
-    //             true,
-    //             None, // Assuming no direction needed or using default direction
-    //         ));
-    //     }
-
-    //     self.set_last_split();
-    // }
-
     //returns the direction based on the current position of the player and the target used with the mouse
     fn calculate_target_direction(&self) -> Point {
         let dx = self.target_x;
@@ -354,82 +430,60 @@ impl Player {
         .normalize()
     }
 
-    // pub fn distribute_mass_randomly(
-    //     &mut self,
-    //     total_mass: TotalMass,
-    //     pieces: usize,
-    //     min_mass: Mass,
-    // ) -> Vec<f32> {
-    //     let mut rng = rand::thread_rng();
-    //     let mut masses = vec![min_mass; pieces];
-    //     let mut remaining_mass = total_mass - min_mass * pieces as f32;
-
-    //     let mut i = 0;
-    //     while remaining_mass > 0.0 {
-    //         let add_mass = (rng.gen::<f32>() * remaining_mass)
-    //             .floor()
-    //             .min(remaining_mass);
-    //         masses[i] += add_mass;
-    //         remaining_mass -= add_mass;
-    //         i = (i + 1) % pieces;
-    //     }
-
-    //     // Shuffle the array to randomize which cells get which mass
-    //     for i in (1..masses.len()).rev() {
-    //         let j = rng.gen_range(0..=i);
-    //         masses.swap(i, j);
-    //     }
-
-    //     masses
-    // }
+    // one charge comes back every recharge seconds, up to the max
+    pub fn update_teleport_charges(&mut self, now: i64) {
+        let teleport = &get_current_config().teleport;
 
-    pub fn teleport(&mut self, new_position: &Point) {
-        if self.total_mass > 150 as usize {
-            return;
+        if self.teleport_charges >= teleport.max_charges {
+            self.recharge_started = now;
+        } else if now - self.recharge_started >= teleport.recharge {
+            self.teleport_charges += 1;
+            self.recharge_started = now;
         }
-        if self.can_teleport {
-            let mut x_sum = 0.0;
-            let mut y_sum = 0.0;
-            let config = get_current_config();
-            for cell in self.cells.iter_mut() {
-                // TODO: remove the enumerate
-                // Assume cell has a method `move` taking necessary parameters
-                // info!("Cell {}", i);
-                cell.move_cell(
-                    &new_position,
-                    self.target_x,
-                    self.target_y,
-                    config.slow_base as f32,
-                    config.get_init_mass_log(),
-                    true,
-                );
-                adjust_for_boundaries(
-                    &mut cell.position.x,
-                    &mut cell.position.y,
-                    cell.position.radius / 3.0,
-                    0.0,
-                    config.game_width as f32,
-                    config.game_height as f32,
-                );
+    }
 
-                x_sum += cell.position.x;
-                y_sum += cell.position.y;
-            }
+    pub fn can_teleport(&self, now: i64) -> bool {
+        let teleport = &get_current_config().teleport;
 
-            if !self.cells.is_empty() {
-                self.x = x_sum / self.cells.len() as f32;
-                self.y = y_sum / self.cells.len() as f32;
-            }
-            self.can_teleport = false;
+        self.teleport_charges > 0
+            && now - self.last_teleport >= teleport.cooldown
+            && (teleport.min_mass..=teleport.max_mass).contains(&self.total_mass)
+    }
+
+    // the cells keep their layout around the new position, returns false if nothing moved
+    pub fn teleport(&mut self, new_position: &Point, now: i64) -> bool {
+        if !self.can_teleport(now) || self.cells.is_empty() {
+            return false;
         }
+
+        let config = get_current_config();
+        let center = self.get_position_point();
+        for cell in self.cells.iter_mut() {
+            let (dx, dy) = center.delta_to(&cell.position);
+            cell.teleport_to(&Point {
+                x: new_position.x + dx,
+                y: new_position.y + dy,
+                radius: 0.0,
+            });
+            keep_in_arena(
+                &mut cell.position.x,
+                &mut cell.position.y,
+                cell.position.radius / 3.0,
+                0.0,
+                config.game_width as f32,
+                config.game_height as f32,
+            );
+            config.arena.push_out_of_obstacles(&mut cell.position);
+        }
+
+        self.x = new_position.x;
+        self.y = new_position.y;
+        self.teleport_charges -= 1;
+        self.last_teleport = now;
+        true
     }
 
-    pub fn virus_split(
-        &mut self,
-        cell_indexes: &[usize],
-        max_cells: usize,
-        default_player_mass: Mass,
-    ) {
+    pub fn virus_split(&mut self, cell_indexes: &[usize], max_cells: usize, pop: &VirusPopConfig) {
         for &cell_index in cell_indexes {
             if cell_index < self.cells.len() {
                 // Safety check to ensure the index is valid
@@ -440,12 +494,77 @@ impl Player {
                     continue;
                 }
 
-                self.split_cell(
-                    cell_index,
-                    max_requested_pieces as u8,
-                    default_player_mass,
-                    Some(std::f32::consts::PI),
-                ); // Example split angle of PI
+                self.pop_cell(cell_index, max_requested_pieces, pop);
+            }
+        }
+    }
+
+    // the cell bursts into pieces thrown all around it
+    fn pop_cell(&mut self, cell_index: usize, max_requested_pieces: usize, pop: &VirusPopConfig) {
+        let (cell_pos_x, cell_pos_y, cell_mass) = {
+            let cell = &self.cells[cell_index];
+            (cell.position.x, cell.position.y, cell.mass)
+        };
+
+        let max_pieces = max_requested_pieces.min(pop.max_pieces as usize);
+        let mut masses = distribute_pop_mass(cell_mass, max_pieces, pop);
+        if masses.len() <= 1 {
+            return;
+        }
+
+        let mut rng = rand::thread_rng();
+        // the cell keeps the biggest piece, the big ones left don't fly side by side
+        masses[1..].shuffle(&mut rng);
+
+        let time_to_merge =
+            get_current_timestamp() + (MERGE_TIMER + (self.total_mass as f32) / 100.0) as i64;
+        self.cells[cell_index].set_mass(masses[0]);
+        self.cells[cell_index].time_to_merge = Some(time_to_merge);
+
+        // evenly spread over the whole circle, with a bit of noise
+        let angle_increment = TAU / (masses.len() - 1) as f32;
+        let angle_base = rng.gen_range(0.0..TAU);
+
+        for (i, mass) in masses[1..].iter().enumerate() {
+            let angle = angle_base + angle_increment * (i as f32 + rng.gen_range(-0.25..0.25));
+            let direction = Point {
+                x: angle.cos(),
+                y: angle.sin(),
+                radius: 0.0,
+            };
+
+            self.cells.push(Cell::new(
+                cell_pos_x,
+                cell_pos_y,
+                *mass,
+                SPLIT_CELL_SPEED,
+                false,
+                Some(direction),
+                Some(time_to_merge),
+            ));
+        }
+
+        self.recalculate_total_mass();
+        self.recalculate_ratio();
+    }
+
+    // cells bigger than max_cell_mass split in two until they fit, while there is room for
+    // more cells. without room they keep growing. the piece flies off in a random direction,
+    // toward the mouse it would be thrown at whatever the player is chasing
+    pub fn auto_split(&mut self, max_cells: usize, max_cell_mass: Mass) {
+        let mut rng = rand::thread_rng();
+        let mut index = 0;
+        while index < self.cells.len() && self.cells.len() < max_cells {
+            if self.cells[index].mass > max_cell_mass.max(1) {
+                let angle = rng.gen_range(0.0..TAU);
+                let direction = Point {
+                    x: angle.cos(),
+                    y: angle.sin(),
+                    radius: 0.0,
+                };
+                self.split_cell(index, 1, 1, direction);
+            } else {
+                index += 1;
             }
         }
     }
@@ -480,11 +599,25 @@ impl Player {
             if self.cells[i].mass < default_player_mass * 2 {
                 break; // break because the cells are sorted by mass, the next cells are smaller than this one
             }
-            self.split_cell(i, 1, default_player_mass, None);
+            let direction = self.calculate_target_direction();
+            self.split_cell(i, 1, default_player_mass, direction);
         }
         self.recalculate_total_mass();
     }
 
+    // successive splits from one input, each one splits the cells left by the previous one
+    pub fn user_split_many(&mut self, count: u8, max_cells: usize, default_player_mass: Mass) {
+        for _ in 0..count.min(MAX_SPLITS_PER_INPUT) {
+            let cells_before = self.cells.len();
+            self.user_split(max_cells, default_player_mass);
+
+            if self.cells.len() == cells_before {
+                break;
+            }
+            self.end_protection();
+        }
+    }
+
     fn sort_by_left(&mut self) {
         self.cells.sort_by(|a, b| {
             (a.position.x - a.position.radius)
@@ -522,44 +655,26 @@ impl Player {
 
     pub fn handle_cells(&mut self) {
         let current_time = get_current_timestamp();
+        let config = get_current_config();
+        let merge_overlap = config.eat_rules.merge_overlap;
         self.enumerate_colliding_cells(|cell_a, cell_b| {
+            // a merge above max_cell_mass would only split again on the next tick
+            let fits = cell_a.mass.saturating_add(cell_b.mass) <= config.max_cell_mass;
             if let (Some(time_a), Some(time_b)) = (cell_a.time_to_merge, cell_b.time_to_merge) {
-                if current_time > time_a && current_time > time_b {
+                if fits && current_time > time_a && current_time > time_b {
                     //Merge cells
                     if !cell_a.to_be_removed && !cell_b.to_be_removed {
-                        if check_overlap(&cell_a.position, &cell_b.position) {
+                        if check_overlap(&cell_a.position, &cell_b.position, merge_overlap) {
                             cell_a.add_mass(cell_b.mass);
                             cell_b.mark_for_removal();
                         }
                     }
                 } else {
-                    let mut vector = Point {
-                        x: cell_b.position.x - cell_a.position.x + 20.0,
-                        y: cell_b.position.y - cell_a.position.y + 20.0,
-                        radius: 0.0,
-                    }
-                    .normalize()
-                    .scale(PUSHING_AWAY_SPEED);
-
-                    cell_a.position.x -= vector.x;
-                    cell_a.position.y -= vector.y;
-                    cell_b.position.x += vector.x;
-                    cell_b.position.y += vector.y;
+                    cell_a.push_away(cell_b);
                 }
             } else {
                 //Push away colliding cells
-                let mut vector = Point {
-                    x: cell_b.position.x - cell_a.position.x + 20.0,
-                    y: cell_b.position.y - cell_a.position.y + 20.0,
-                    radius: 0.0,
-                }
-                .normalize()
-                .scale(PUSHING_AWAY_SPEED);
-
-                cell_a.position.x -= vector.x;
-                cell_a.position.y -= vector.y;
-                cell_b.position.x += vector.x;
-                cell_b.position.y += vector.y;
+                cell_a.push_away(cell_b);
             }
         });
         //Remove all cells marked for removal (merged)
@@ -576,39 +691,310 @@ impl Player {
         let mut x_sum = 0.0;
         let mut y_sum = 0.0;
 
+        let config = get_current_config();
         let player_position = self.get_position_point();
+        let speed_factor = if self.has_effect(PowerUpKind::Speed) {
+            config.power_ups.speed_multiplier
+        } else {
+            1.0
+        };
+
+        let reference = match self.cells.first() {
+            Some(cell) => cell.position,
+            None => return,
+        };
+        let now = get_current_timestamp();
+
         for cell in self.cells.iter_mut() {
-            // TODO: remove the enumerate
-            // Assume cell has a method `move` taking necessary parameters
-            // info!("Cell {}", i);
-            cell.move_cell(
-                &player_position,
-                self.target_x,
-                self.target_y,
-                slow_base,
-                init_mass_log,
-                false,
-            );
-            adjust_for_boundaries(
-                &mut cell.position.x,
-                &mut cell.position.y,
-                cell.position.radius / 3.0,
-                0.0,
-                game_width as f32,
-                game_height as f32,
-            );
+            // a frozen player holds its cells still, split cells still finish their flight and
+            // the cells ready to merge are still pulled together, to the center instead of the mouse
+            if !self.frozen || cell.is_flying() {
+                cell.move_cell(
+                    &player_position,
+                    self.target_x,
+                    self.target_y,
+                    slow_base,
+                    init_mass_log,
+                    speed_factor,
+                );
+            } else if cell.time_to_merge.is_some_and(|time| now > time) {
+                cell.move_cell(
+                    &player_position,
+                    0.0,
+                    0.0,
+                    slow_base,
+                    init_mass_log,
+                    speed_factor,
+                );
+            }
+            let keep_in = |position: &mut Point| {
+                keep_in_arena(
+                    &mut position.x,
+                    &mut position.y,
+                    position.radius / 3.0,
+                    0.0,
+                    game_width as f32,
+                    game_height as f32,
+                )
+            };
+            keep_in(&mut cell.position);
+            // an obstacle on the border can push the cell out, it is brought back once more
+            if config.arena.push_out_of_obstacles(&mut cell.position) {
+                keep_in(&mut cell.position);
+            }
 
-            x_sum += cell.position.x;
-            y_sum += cell.position.y;
+            // relative to the first cell, so cells on both sides of a wrapping edge average right
+            let (dx, dy) = reference.delta_to(&cell.position);
+            x_sum += dx;
+            y_sum += dy;
         }
 
-        if !self.cells.is_empty() {
-            self.x = x_sum / self.cells.len() as f32;
-            self.y = y_sum / self.cells.len() as f32;
-        }
+        self.x = reference.x + x_sum / self.cells.len() as f32;
+        self.y = reference.y + y_sum / self.cells.len() as f32;
+        keep_in_arena(
+            &mut self.x,
+            &mut self.y,
+            0.0,
+            0.0,
+            game_width as f32,
+            game_height as f32,
+        );
 
         if self.cells.len() > 1 {
             self.handle_cells();
         }
     }
-}
\ No newline at end of file
+}
+
+// masses of the pieces of a popped cell, biggest first, the first one stays in the cell.
+// a few big pieces share most of the spare mass, the small ones split the rest evenly
+pub fn distribute_pop_mass(
+    cell_mass: Mass,
+    max_new_pieces: usize,
+    pop: &VirusPopConfig,
+) -> Vec<Mass> {
+    let min_mass = pop.min_piece_mass.max(1);
+    let pieces = ((cell_mass / min_mass) as usize).min(max_new_pieces + 1);
+    if pieces <= 1 {
+        return vec![cell_mass];
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut masses = vec![min_mass; pieces];
+    let mut spare_mass = cell_mass - min_mass * pieces as Mass;
+
+    let big_pieces = (pop.big_pieces as usize).clamp(1, pieces);
+    let mut big_mass = (spare_mass as f32 * pop.big_share.clamp(0.0, 1.0)) as Mass;
+    spare_mass -= big_mass;
+
+    for (i, mass) in masses[..big_pieces].iter_mut().enumerate() {
+        let share = if i == big_pieces - 1 {
+            big_mass
+        } else {
+            (big_mass as f32 * rng.gen_range(0.3..0.7)) as Mass
+        };
+        *mass += share;
+        big_mass -= share;
+    }
+
+    // without small pieces the rest goes to the big ones
+    let small_start = if big_pieces < pieces { big_pieces } else { 0 };
+    let small_pieces = (pieces - small_start) as Mass;
+    for (i, mass) in masses[small_start..].iter_mut().enumerate() {
+        *mass += spare_mass / small_pieces + Mass::from((i as Mass) < spare_mass % small_pieces);
+    }
+
+    masses.sort_unstable_by_key(|mass| std::cmp::Reverse(*mass));
+    masses
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::{get_current_config, VirusPopConfig},
+        map::{cell::Cell, point::Point},
+        utils::consts::{Mass, MIN_SPEED},
+    };
+
+    use super::{distribute_pop_mass, Player};
+
+    #[test]
+    fn test_pop_mass_is_conserved() {
+        let pop = VirusPopConfig::default();
+
+        for cell_mass in [5, 10, 19, 35, 150, 333, 1_000, 4_321, Mass::MAX] {
+            for max_new_pieces in [0, 1, 3, 15, 100] {
+                let masses = distribute_pop_mass(cell_mass, max_new_pieces, &pop);
+
+                assert_eq!(masses.iter().map(|m| *m as usize).sum::<usize>(), cell_mass as usize);
+                assert!(masses.len() <= max_new_pieces + 1);
+                assert!(masses.windows(2).all(|pair| pair[0] >= pair[1]));
+                if masses.len() > 1 {
+                    assert!(masses.iter().all(|mass| *mass >= pop.min_piece_mass));
+                }
+            }
+        }
+
+        // big pieces and small pieces
+        let masses = distribute_pop_mass(1_000, 15, &pop);
+        assert_eq!(masses.len(), 16);
+        assert!(masses[0] > masses[15] * 2);
+    }
+
+    #[test]
+    fn test_virus_pop_respects_the_split_limit() {
+        let pop = VirusPopConfig::default();
+        let mut player = Player::new(0);
+        player.reset(&Point { x: 500.0, y: 500.0, radius: 0.0 }, 400);
+
+        player.virus_split(&[0], 8, &pop);
+        assert_eq!(player.cells.len(), 8);
+        assert_eq!(player.total_mass, 400);
+
+        // already at the limit, nothing happens
+        player.virus_split(&[0], 8, &pop);
+        assert_eq!(player.cells.len(), 8);
+        assert_eq!(player.total_mass, 400);
+    }
+
+    #[test]
+    fn test_multi_split_in_one_input() {
+        let spawn = Point { x: 5_000.0, y: 5_000.0, radius: 0.0 };
+
+        let mut player = Player::new(0);
+        player.reset(&spawn, 1_600);
+        player.user_split_many(4, 16, 10);
+        assert_eq!(player.cells.len(), 16);
+        assert_eq!(player.total_mass, 1_600);
+
+        // stops at limit_split
+        let mut player = Player::new(0);
+        player.reset(&spawn, 1_600);
+        player.user_split_many(3, 6, 10);
+        assert_eq!(player.cells.len(), 6);
+
+        // and when the cells get too small to split
+        let mut player = Player::new(0);
+        player.reset(&spawn, 35);
+        player.user_split_many(4, 16, 10);
+        assert_eq!(player.cells.len(), 2);
+    }
+
+    #[test]
+    fn test_frozen_cells_hold_still() {
+        let config = get_current_config();
+        let spawn = Point { x: 5_000.0, y: 5_000.0, radius: 0.0 };
+        let mut player = Player::new(0);
+        player.reset(&spawn, 400);
+        player.target_x = 300.0;
+        player.frozen = true;
+
+        player.move_cells(
+            config.slow_base as f32,
+            config.game_width as i32,
+            config.game_height as i32,
+            config.get_init_mass_log(),
+        );
+        assert_eq!((player.cells[0].position.x, player.cells[0].position.y), (5_000.0, 5_000.0));
+
+        // the split cell still flies out
+        player.user_split(16, 10);
+        let before = player.cells[1].position.x;
+        player.move_cells(
+            config.slow_base as f32,
+            config.game_width as i32,
+            config.game_height as i32,
+            config.get_init_mass_log(),
+        );
+        assert!(player.cells[1].position.x > before + 10.0);
+
+        // cells ready to merge are still pulled together
+        let mut player = Player::new(0);
+        player.reset(&spawn, 400);
+        player.cells.push(Cell::new(5_300.0, 5_000.0, 400, MIN_SPEED, true, None, Some(0)));
+        player.cells[0].time_to_merge = Some(0);
+        player.recalculate_total_mass();
+        player.frozen = true;
+
+        let gap = player.cells[1].position.x - player.cells[0].position.x;
+        player.move_cells(
+            config.slow_base as f32,
+            config.game_width as i32,
+            config.game_height as i32,
+            config.get_init_mass_log(),
+        );
+        assert!(player.cells[1].position.x - player.cells[0].position.x < gap);
+    }
+
+    #[test]
+    fn test_spawn_protection_ends_on_split() {
+        let config = get_current_config();
+        let mut player = Player::new(0);
+        player.reset(&Point { x: 500.0, y: 500.0, radius: 0.0 }, 10);
+
+        let now = crate::utils::util::get_current_timestamp();
+        assert!(player.is_protected(now));
+        assert!(!player.is_protected(now + config.spawn_protection));
+
+        // too small to split, still protected
+        player.user_split_many(1, 16, config.split_min_mass);
+        assert!(player.is_protected(now));
+
+        player.cells[0].add_mass(100);
+        player.user_split_many(1, 16, config.split_min_mass);
+        assert!(!player.is_protected(now));
+    }
+
+    #[test]
+    fn test_teleport_charges_recharge() {
+        let teleport = &get_current_config().teleport;
+        let mut player = Player::new(0);
+        player.reset(&Point { x: 500.0, y: 500.0, radius: 0.0 }, 10);
+        player.cells[0].teleport_to(&Point { x: 550.0, y: 500.0, radius: 0.0 });
+
+        let now = 1_000;
+        player.update_teleport_charges(now);
+        let arrival = Point { x: 2_000.0, y: 2_000.0, radius: 0.0 };
+        for _ in 0..teleport.max_charges {
+            assert!(player.teleport(&arrival, now + teleport.cooldown * 2));
+        }
+        // the cell keeps its offset to the player
+        assert_eq!(player.cells[0].position.x, 2_050.0);
+
+        let used = now + teleport.cooldown * 2;
+        assert!(!player.can_teleport(used + teleport.cooldown));
+        player.update_teleport_charges(used + teleport.recharge);
+        assert!(player.can_teleport(used + teleport.recharge));
+
+        player.cells[0].add_mass(teleport.max_mass as Mass);
+        player.recalculate_total_mass();
+        assert!(!player.can_teleport(used + teleport.recharge));
+    }
+
+    #[test]
+    fn test_big_cells_auto_split_without_losing_mass() {
+        let mut player = Player::new(0);
+        player.reset(&Point { x: 5_000.0, y: 5_000.0, radius: 0.0 }, 60_000);
+        player.cells[0].add_mass(40_001);
+        assert_eq!(player.cells[0].mass, 100_001);
+
+        player.auto_split(16, 22_500);
+        assert_eq!(player.cells.len(), 8);
+        assert!(player.cells.iter().all(|cell| cell.mass <= 22_500));
+        assert_eq!(player.total_mass, 100_001);
+
+        // the pieces are ready to merge, but any two of them are above the max
+        for cell in player.cells.iter_mut() {
+            cell.time_to_merge = Some(0);
+        }
+        player.handle_cells();
+        assert_eq!(player.cells.len(), 8);
+
+        // no room left, the cell keeps its mass
+        let mut player = Player::new(0);
+        player.reset(&Point { x: 5_000.0, y: 5_000.0, radius: 0.0 }, 100_000);
+        player.auto_split(1, 22_500);
+        assert_eq!(player.cells.len(), 1);
+        assert_eq!(player.total_mass, 100_000);
+    }
+}
@@ -3,6 +3,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::{config::get_current_config, utils::game_logic::wrapped_delta};
+
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
 pub struct Point {
     pub x: f32,
@@ -32,8 +34,14 @@ impl Point {
         }
     }
 
+    // offset to other, the shortest one through the edges on a wrap around arena
+    pub fn delta_to(&self, other: &Point) -> (f32, f32) {
+        wrapped_delta(self, other, get_current_config().arena.wrap_size())
+    }
+
     pub fn distance_pow(&self, other: &Point) -> f32 {
-        (self.x - other.x).powf(2.0) + (self.y - other.y).powf(2.0)
+        let (dx, dy) = self.delta_to(other);
+        dx * dx + dy * dy
     }
 
     pub fn distance(&self, other: &Point) -> f32 {
@@ -0,0 +1,64 @@
+use super::point::{AsPoint, Point};
+use crate::utils::id::PowerUpID;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub const POWER_UP_RADIUS: f32 = 30.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerUpKind {
+    Speed,
+    MergeReset,
+    Shield,
+    Magnet,
+}
+
+impl PowerUpKind {
+    const ALL: [PowerUpKind; 4] = [
+        PowerUpKind::Speed,
+        PowerUpKind::MergeReset,
+        PowerUpKind::Shield,
+        PowerUpKind::Magnet,
+    ];
+
+    pub fn random() -> Self {
+        Self::ALL[rand::thread_rng().gen_range(0..Self::ALL.len())]
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerUp {
+    pub id: PowerUpID,
+    pub kind: PowerUpKind,
+    pub x: f32,
+    pub y: f32,
+}
+
+impl PowerUp {
+    pub fn new(id: PowerUpID, kind: PowerUpKind, position: &Point) -> Self {
+        PowerUp {
+            id,
+            kind,
+            x: position.x,
+            y: position.y,
+        }
+    }
+}
+
+impl AsPoint for PowerUp {
+    fn as_point(&self) -> Point {
+        Point {
+            x: self.x,
+            y: self.y,
+            radius: POWER_UP_RADIUS,
+        }
+    }
+}
+
+// an effect picked by a player, active until the timestamp (in seconds)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ActiveEffect {
+    pub kind: PowerUpKind,
+    pub until: i64,
+}
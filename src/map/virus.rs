@@ -1,9 +1,12 @@
-use super::point::Point;
+use super::point::{AsPoint, Point};
 use crate::utils::consts::Mass;
-use crate::utils::game_logic::adjust_for_boundaries;
+use crate::config::get_current_config;
+use crate::utils::game_logic::keep_in_arena;
 use crate::utils::id::VirusID;
-use crate::utils::util::{check_overlap, mass_to_radius};
-use serde::Serialize;
+use crate::utils::quad_tree::Indexable;
+use crate::utils::rules::EatRule;
+use crate::utils::util::mass_to_radius;
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Clone)]
 pub struct VirusData {
@@ -13,7 +16,7 @@ pub struct VirusData {
     pub mass: Mass,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Virus {
     pub id: VirusID,
     x: f32,
@@ -46,9 +49,8 @@ impl Virus {
         }
     }
 
-    pub fn can_be_eat_by(&self, cell_mass: Mass, cell_point: &Point) -> bool {
-        (cell_mass as f32) > (1.1 * (self.mass as f32))
-            && check_overlap(&self.get_position(), &cell_point)
+    pub fn can_be_eat_by(&self, rule: &EatRule, cell_mass: Mass, cell_point: &Point) -> bool {
+        rule.can_eat(cell_mass, cell_point, self.mass, &self.get_position())
     }
 
     pub fn get_position(&self) -> Point {
@@ -63,23 +65,49 @@ impl Virus {
         self.speed = Some(new_speed);
     }
 
+    // adds a push to the current movement of the virus
+    pub fn push(&mut self, direction: &Point, strength: f32) {
+        let speed = self.speed.unwrap_or_default();
+        let (velocity_x, velocity_y) = match self.direction {
+            Some(dir) => (dir.x * speed, dir.y * speed),
+            None => (0.0, 0.0),
+        };
+
+        let velocity = Point {
+            x: velocity_x + direction.x * strength,
+            y: velocity_y + direction.y * strength,
+            radius: 0.0,
+        };
+        let new_speed = velocity.x.hypot(velocity.y);
+
+        if new_speed > 0.0 {
+            self.direction = Some(velocity.normalize());
+            self.speed = Some(new_speed);
+        }
+    }
+
     pub fn move_virus(&mut self, game_width: f32, game_height: f32) {
-        if let Some(speed) = self.speed {
-            if let Some(dir) = &self.direction {
-                let delta_x = speed * dir.x;
-                let delta_y = speed * dir.y;
-                self.speed = Some(speed - 1.0);
-                if self.speed.unwrap() < 0.0 {
-                    self.speed = None;
-                }
+        if let (Some(speed), Some(dir)) = (self.speed, self.direction.as_mut()) {
+            self.x += speed * dir.x;
+            self.y += speed * dir.y;
 
-                self.x += delta_x;
-                self.y += delta_y;
+            self.speed = Some(speed - 1.0);
+            if speed - 1.0 < 0.0 {
+                self.speed = None;
             }
-        }
 
-        if self.speed.is_some() {
-            adjust_for_boundaries(
+            // bounce off the borders instead of sliding along them
+            let border = self.radius + 5.0;
+            if !get_current_config().arena.wrap {
+                if self.x < border || self.x > game_width - border {
+                    dir.x = -dir.x;
+                }
+                if self.y < border || self.y > game_height - border {
+                    dir.y = -dir.y;
+                }
+            }
+
+            keep_in_arena(
                 &mut self.x,
                 &mut self.y,
                 self.radius,
@@ -103,3 +131,57 @@ impl Virus {
         self.radius = mass_to_radius(self.mass);
     }
 }
+
+impl AsPoint for Virus {
+    fn as_point(&self) -> Point {
+        self.get_position()
+    }
+}
+
+impl Indexable for Virus {
+    type ID = VirusID;
+
+    fn get_id(&self) -> VirusID {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::point::Point;
+
+    use super::Virus;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point { x, y, radius: 0.0 }
+    }
+
+    #[test]
+    fn test_pushed_virus_bounces_off_the_border() {
+        let mut virus = Virus::new(0, point(950.0, 500.0), 100, None);
+        virus.push(&point(1.0, 0.0), 20.0);
+        assert_eq!(virus.speed, Some(20.0));
+
+        let mut max_x: f32 = 0.0;
+        while virus.speed.is_some() {
+            virus.move_virus(1_000.0, 1_000.0);
+            max_x = max_x.max(virus.x);
+        }
+
+        assert!(max_x <= 1_000.0 - virus.radius);
+        // came back from the right border
+        assert!(virus.x < 950.0);
+        assert_eq!(virus.y, 500.0);
+    }
+
+    #[test]
+    fn test_pushes_add_up() {
+        let mut virus = Virus::new(0, point(500.0, 500.0), 100, None);
+        virus.push(&point(1.0, 0.0), 3.0);
+        virus.push(&point(0.0, 1.0), 4.0);
+
+        assert_eq!(virus.speed, Some(5.0));
+        let direction = virus.direction.unwrap();
+        assert!((direction.x - 0.6).abs() < 1e-6 && (direction.y - 0.8).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use crate::{
+    config::get_current_config,
+    map::player::Player,
+    send_messages::{LeaderboardPlayer, ZoneMessage},
+    utils::{consts::Mass, id::PlayerID, quad_tree::Rectangle, util::lerp},
+};
+
+use super::GameMode;
+
+// the safe zone at `now` (seconds, with fraction), it shrinks toward the center of the map
+pub fn zone_at(round_start: i64, now: f64) -> Rectangle {
+    let config = get_current_config();
+    let battle_royale = &config.battle_royale;
+    let (width, height) = (config.game_width as f32, config.game_height as f32);
+
+    let shrink_start = (round_start + battle_royale.shrink_delay) as f64;
+    let progress = ((now - shrink_start) / battle_royale.shrink_duration.max(1) as f64)
+        .clamp(0.0, 1.0) as f32;
+
+    let w = lerp(width, battle_royale.min_zone_size.min(width), progress);
+    let h = lerp(height, battle_royale.min_zone_size.min(height), progress);
+
+    Rectangle::new((width - w) / 2.0, (height - h) / 2.0, w, h)
+}
+
+// cells outside the zone lose mass until they vanish, the stakes of the dead go to a pot
+// paid to the last player alive. once the round is contested nobody else spawns, late
+// players spectate until the next round
+pub struct BattleRoyale {
+    round_start: AtomicI64,
+    zone: Mutex<Rectangle>,
+    // the players who spawned this round and did not die or leave since
+    alive: Mutex<HashSet<PlayerID>>,
+    // more than one player was alive during this round
+    contested: AtomicBool,
+    pot: AtomicU64,
+}
+
+impl BattleRoyale {
+    pub fn new(now: i64) -> Self {
+        BattleRoyale {
+            round_start: AtomicI64::new(now),
+            zone: Mutex::new(zone_at(now, now as f64)),
+            alive: Mutex::new(HashSet::new()),
+            contested: AtomicBool::new(false),
+            pot: AtomicU64::new(0),
+        }
+    }
+
+    fn get_zone(&self) -> Rectangle {
+        *self.zone.lock().unwrap()
+    }
+}
+
+impl GameMode for BattleRoyale {
+    fn name(&self) -> &'static str {
+        "battle_royale"
+    }
+
+    fn on_tick(&self, now: i64) {
+        let round_start = self.round_start.load(Ordering::Relaxed);
+        *self.zone.lock().unwrap() = zone_at(round_start, now as f64 / 1_000_000.0);
+    }
+
+    // a player leaving is not beaten, the round opens again so the rest have to win it
+    // against someone new
+    fn on_leave(&self, player_id: PlayerID) {
+        if self.alive.lock().unwrap().remove(&player_id) {
+            self.contested.store(false, Ordering::Relaxed);
+        }
+    }
+
+    fn on_spawn(&self, player: &Player) -> bool {
+        if self.contested.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        self.alive.lock().unwrap().insert(player.id);
+        true
+    }
+
+    fn allows_bots(&self) -> bool {
+        false
+    }
+
+    fn on_round_start(&self, now: i64) {
+        self.round_start.store(now, Ordering::Relaxed);
+        self.alive.lock().unwrap().clear();
+        self.contested.store(false, Ordering::Relaxed);
+        *self.zone.lock().unwrap() = zone_at(now, now as f64);
+    }
+
+    // players spawn inside the zone too
+    fn spawn_area(&self) -> Rectangle {
+        self.get_zone()
+    }
+
+    fn zone(&self) -> Option<ZoneMessage> {
+        let battle_royale = &get_current_config().battle_royale;
+        let zone = self.get_zone();
+        let shrink_start = self.round_start.load(Ordering::Relaxed) + battle_royale.shrink_delay;
+        let final_zone = zone_at(shrink_start, f64::MAX);
+
+        Some(ZoneMessage {
+            x: zone.x,
+            y: zone.y,
+            w: zone.w,
+            h: zone.h,
+            final_w: final_zone.w,
+            final_h: final_zone.h,
+            shrink_start,
+            shrink_end: shrink_start + battle_royale.shrink_duration,
+        })
+    }
+
+    fn update_player(&self, player: &mut Player) {
+        let zone = self.get_zone();
+        let damage_rate = get_current_config().battle_royale.zone_damage_rate / 1000.0;
+
+        for cell in player.cells.iter_mut() {
+            if !zone.contains(&cell.position) {
+                let damage = ((cell.mass as f32) * damage_rate).max(1.0) as Mass;
+                cell.remove_mass(damage);
+            }
+        }
+
+        player.cells.retain(|cell| cell.mass > 0);
+    }
+
+    fn on_death(&self, player: &Player, _killer: Option<&Player>) {
+        self.alive.lock().unwrap().remove(&player.id);
+    }
+
+    fn collect_stake(&self, stake: u64) -> bool {
+        self.pot.fetch_add(stake, Ordering::Relaxed);
+        true
+    }
+
+    fn take_pot(&self) -> u64 {
+        self.pot.swap(0, Ordering::Relaxed)
+    }
+
+    // the standings also hold spectators, only who is still alive counts
+    fn winner(&self, _standings: &[LeaderboardPlayer]) -> Option<PlayerID> {
+        let alive = self.alive.lock().unwrap();
+        match alive.len() {
+            0 => None,
+            1 => self
+                .contested
+                .load(Ordering::Relaxed)
+                .then(|| *alive.iter().next().unwrap()),
+            _ => {
+                self.contested.store(true, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::get_current_config, map::player::Player, modes::GameMode};
+
+    use super::{zone_at, BattleRoyale};
+
+    #[test]
+    fn test_zone_shrinks_to_the_center() {
+        let config = get_current_config();
+        let battle_royale = &config.battle_royale;
+        let (width, height) = (config.game_width as f32, config.game_height as f32);
+        let shrink_start = 1_000 + battle_royale.shrink_delay;
+
+        let zone = zone_at(1_000, shrink_start as f64);
+        assert_eq!((zone.x, zone.y, zone.w, zone.h), (0.0, 0.0, width, height));
+
+        let half_way = shrink_start as f64 + battle_royale.shrink_duration as f64 / 2.0;
+        let zone = zone_at(1_000, half_way);
+        assert_eq!(zone.w, (width + battle_royale.min_zone_size) / 2.0);
+        assert_eq!(zone.x + zone.w / 2.0, width / 2.0);
+
+        let zone = zone_at(1_000, f64::MAX);
+        assert_eq!(zone.w, battle_royale.min_zone_size);
+        assert_eq!(zone.h, battle_royale.min_zone_size);
+        assert_eq!(zone.y + zone.h / 2.0, height / 2.0);
+    }
+
+    #[test]
+    fn test_last_player_alive_wins_the_pot() {
+        let mode = BattleRoyale::new(0);
+        let players: Vec<_> = (1..=3).map(Player::new).collect();
+
+        // alone from the start, nothing to win
+        assert!(mode.on_spawn(&players[0]));
+        assert_eq!(mode.winner(&[]), None);
+
+        assert!(mode.on_spawn(&players[1]));
+        assert_eq!(mode.winner(&[]), None);
+        // the round is running, late players spectate
+        assert!(!mode.on_spawn(&players[2]));
+
+        assert!(mode.collect_stake(50));
+        assert!(mode.collect_stake(25));
+        mode.on_death(&players[0], Some(&players[1]));
+        assert_eq!(mode.winner(&[]), Some(2));
+        assert_eq!(mode.take_pot(), 75);
+        assert_eq!(mode.take_pot(), 0);
+
+        mode.on_round_start(100);
+        assert_eq!(mode.winner(&[]), None);
+        assert!(mode.on_spawn(&players[2]));
+    }
+
+    #[test]
+    fn test_leaving_does_not_hand_the_win() {
+        let mode = BattleRoyale::new(0);
+        let players: Vec<_> = (1..=3).map(Player::new).collect();
+
+        assert!(mode.on_spawn(&players[0]));
+        assert!(mode.on_spawn(&players[1]));
+        assert_eq!(mode.winner(&[]), None);
+
+        mode.on_leave(1);
+        assert_eq!(mode.winner(&[]), None);
+
+        // open again, the one left has to beat a new player
+        assert!(mode.on_spawn(&players[2]));
+        assert_eq!(mode.winner(&[]), None);
+        mode.on_death(&players[2], None);
+        assert_eq!(mode.winner(&[]), Some(2));
+    }
+}
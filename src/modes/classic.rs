@@ -0,0 +1,10 @@
+use super::GameMode;
+
+// free for all, everybody can eat everybody and the round never ends by itself
+pub struct Classic;
+
+impl GameMode for Classic {
+    fn name(&self) -> &'static str {
+        "classic"
+    }
+}
@@ -0,0 +1,115 @@
+pub mod battle_royale;
+pub mod classic;
+pub mod teams;
+
+use log::error;
+
+use crate::{
+    config::get_current_config,
+    map::{player::Player, point::Point},
+    send_messages::{LeaderboardMessage, LeaderboardPlayer, ZoneMessage},
+    utils::{
+        id::PlayerID,
+        quad_tree::Rectangle,
+        spawn::{spawn_position, SpawnContext},
+        util::get_current_timestamp,
+    },
+};
+
+use self::{battle_royale::BattleRoyale, classic::Classic, teams::Teams};
+
+// Hooks called by the game loop, every hook has the classic behavior as default so a mode
+// only overrides what it changes. Hooks run while the game holds its locks, keep them cheap.
+pub trait GameMode: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // the player was added to the arena, its id is already set
+    fn on_join(&self, _player: &mut Player) {}
+
+    // the player was removed from the arena (kicked, cashed out or dead)
+    fn on_leave(&self, _player_id: PlayerID) {}
+
+    // the player asks to (re)spawn, returning false keeps it spectating until the next round
+    fn on_spawn(&self, _player: &Player) -> bool {
+        true
+    }
+
+    // bots fill the arena up to bots.fill_to when the mode lets them in
+    fn allows_bots(&self) -> bool {
+        true
+    }
+
+    // where the player (re)spawns or teleports, None when there is no safe spot right now
+    fn spawn_point(&self, _player: &Player, context: &SpawnContext) -> Option<Point> {
+        spawn_position(context, &self.spawn_area())
+    }
+
+    // called once per tick before the players are updated, now is in microseconds
+    fn on_tick(&self, _now: i64) {}
+
+    // a new round started after the previous one ended
+    fn on_round_start(&self, _now: i64) {}
+
+    // where new food can appear
+    fn spawn_area(&self) -> Rectangle {
+        let config = get_current_config();
+        Rectangle::new(
+            0.0,
+            0.0,
+            config.game_width as f32,
+            config.game_height as f32,
+        )
+    }
+
+    // the safe zone sent to every player once per game loop, if the mode has one
+    fn zone(&self) -> Option<ZoneMessage> {
+        None
+    }
+
+    // called once per tick for every player, after its cells moved
+    fn update_player(&self, _player: &mut Player) {}
+
+    // a cell of `eater` covers a cell of `eaten`, returning false cancels the kill
+    fn can_eat(&self, _eater: &Player, _eaten: &Player) -> bool {
+        true
+    }
+
+    // `player` lost its last cell, to `killer` or to the mode itself
+    fn on_death(&self, _player: &Player, _killer: Option<&Player>) {}
+
+    // offered the stake lost by a dead player, returning true keeps it in the mode's pot
+    // instead of paying it to the killer
+    fn collect_stake(&self, _stake: u64) -> bool {
+        false
+    }
+
+    // empties the pot, paid to the winner of the round
+    fn take_pot(&self) -> u64 {
+        0
+    }
+
+    // `standings` has every player sorted by mass, biggest first
+    fn leaderboard(&self, standings: Vec<LeaderboardPlayer>) -> LeaderboardMessage {
+        LeaderboardMessage {
+            leaderboard: standings.into_iter().take(10).collect(),
+            teams: vec![],
+        }
+    }
+
+    // returns the winner when the current round is decided, ending it early
+    fn winner(&self, _standings: &[LeaderboardPlayer]) -> Option<PlayerID> {
+        None
+    }
+}
+
+pub fn create_game_mode(name: &str) -> Box<dyn GameMode> {
+    match name {
+        "classic" => Box::new(Classic),
+        "teams" => Box::new(Teams::new(get_current_config().teams)),
+        "battle_royale" => Box::new(BattleRoyale::new(get_current_timestamp())),
+        name => {
+            error!("Unknown game mode [{}], using classic", name);
+            Box::new(Classic)
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::{
+    map::player::Player,
+    send_messages::{LeaderboardMessage, LeaderboardPlayer, LeaderboardTeam},
+    utils::id::PlayerID,
+};
+
+use super::GameMode;
+
+const TEAM_HUES: [u16; 3] = [0, 120, 240];
+
+// players join the smallest team, teammates can't eat each other
+pub struct Teams {
+    count: u8,
+    members: Mutex<HashMap<PlayerID, u8>>,
+}
+
+impl Teams {
+    pub fn new(count: u8) -> Self {
+        Teams {
+            count: count.clamp(2, TEAM_HUES.len() as u8),
+            members: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn team_sizes(members: &HashMap<PlayerID, u8>, count: u8) -> Vec<usize> {
+        let mut sizes = vec![0; count as usize];
+        for team in members.values() {
+            sizes[*team as usize] += 1;
+        }
+        sizes
+    }
+}
+
+impl GameMode for Teams {
+    fn name(&self) -> &'static str {
+        "teams"
+    }
+
+    fn on_join(&self, player: &mut Player) {
+        let mut members = self.members.lock().unwrap();
+        let sizes = Self::team_sizes(&members, self.count);
+
+        // the first smallest team, keeps the teams balanced
+        let team = (0..self.count)
+            .min_by_key(|team| sizes[*team as usize])
+            .unwrap_or_default();
+
+        members.insert(player.id, team);
+        player.team = Some(team);
+        player.hue = TEAM_HUES[team as usize];
+    }
+
+    fn on_leave(&self, player_id: PlayerID) {
+        self.members.lock().unwrap().remove(&player_id);
+    }
+
+    fn can_eat(&self, eater: &Player, eaten: &Player) -> bool {
+        eater.team.is_none() || eater.team != eaten.team
+    }
+
+    fn leaderboard(&self, standings: Vec<LeaderboardPlayer>) -> LeaderboardMessage {
+        let members = self.members.lock().unwrap();
+
+        let mut teams: Vec<LeaderboardTeam> = (0..self.count)
+            .map(|team| LeaderboardTeam {
+                team,
+                hue: TEAM_HUES[team as usize],
+                mass: 0,
+                players: 0,
+            })
+            .collect();
+
+        for player in standings.iter() {
+            if let Some(team) = members.get(&player.id) {
+                let team = &mut teams[*team as usize];
+                team.mass = team.mass.saturating_add(player.mass);
+                team.players += 1;
+            }
+        }
+
+        teams.sort_by_key(|team| std::cmp::Reverse(team.mass));
+
+        LeaderboardMessage {
+            leaderboard: vec![],
+            teams,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{map::player::Player, modes::GameMode, send_messages::LeaderboardPlayer};
+
+    use super::Teams;
+
+    #[test]
+    fn test_teams_stay_balanced() {
+        let teams = Teams::new(3);
+        let mut players: Vec<Player> = (0..7).map(Player::new).collect();
+
+        for player in players.iter_mut() {
+            teams.on_join(player);
+        }
+
+        let assigned: Vec<u8> = players.iter().map(|p| p.team.unwrap()).collect();
+        assert_eq!(assigned, vec![0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(players[1].hue, players[4].hue);
+
+        // team 1 is now the smallest one
+        teams.on_leave(1);
+        teams.on_leave(4);
+        let mut new_player = Player::new(7);
+        teams.on_join(&mut new_player);
+        assert_eq!(new_player.team, Some(1));
+
+        assert!(!teams.can_eat(&players[0], &players[3]));
+        assert!(teams.can_eat(&players[0], &players[2]));
+    }
+
+    #[test]
+    fn test_teams_leaderboard() {
+        let teams = Teams::new(2);
+        for id in 0..4 {
+            teams.on_join(&mut Player::new(id));
+        }
+
+        let standings = (0..4)
+            .map(|id| LeaderboardPlayer {
+                id,
+                mass: 100 * (id as usize + 1),
+                total_won: 0,
+            })
+            .collect();
+
+        let leaderboard = teams.leaderboard(standings);
+        assert!(leaderboard.leaderboard.is_empty());
+
+        // team 0 = players 0 and 2, team 1 = players 1 and 3
+        let totals: Vec<(u8, usize, usize)> = leaderboard
+            .teams
+            .iter()
+            .map(|team| (team.team, team.mass, team.players))
+            .collect();
+        assert_eq!(totals, vec![(1, 600, 2), (0, 400, 2)]);
+    }
+}
@@ -17,7 +17,11 @@ pub enum RecvEvent {
     PlayerGotIt,
     LetMeIn,
     Teleport,
-    Cashout
+    Cashout,
+    AdminCommand,
+    StartFeeding,
+    StopFeeding,
+    Freeze,
 }
 
 impl From<u8> for RecvEvent {
@@ -39,6 +43,10 @@ impl From<&str> for RecvEvent {
             "gotit" => RecvEvent::PlayerGotIt,
             "3" => RecvEvent::Teleport,
             "4" => RecvEvent::Cashout,
+            "admin" => RecvEvent::AdminCommand,
+            "5" => RecvEvent::StartFeeding,
+            "6" => RecvEvent::StopFeeding,
+            "7" => RecvEvent::Freeze,
             event => {
                 error!("RecvEvent not implement from string for: {}", event);
                 todo!()
@@ -59,7 +67,11 @@ impl Display for RecvEvent {
             RecvEvent::PlayerChat => "playerChat",
             RecvEvent::PlayerGotIt => "gotit",
             RecvEvent::Teleport => "3",
-            RecvEvent::Cashout => "4"
+            RecvEvent::Cashout => "4",
+            RecvEvent::AdminCommand => "admin",
+            RecvEvent::StartFeeding => "5",
+            RecvEvent::StopFeeding => "6",
+            RecvEvent::Freeze => "7",
         })
     }
 }
@@ -124,6 +136,18 @@ pub struct Target {
     pub y: f32,
 }
 
+// a packet without value is a single split
+#[derive(Deserialize)]
+pub struct SplitMessage {
+    pub count: u8,
+}
+
+// the client sends the state it wants, a lost or repeated packet can't flip it the wrong way
+#[derive(Deserialize)]
+pub struct FreezeMessage {
+    pub frozen: bool,
+}
+
 #[derive(Deserialize)]
 pub struct UserIdMessage {
     pub user_id: Option<String>,
@@ -136,6 +160,12 @@ pub struct LetMeInMessage {
     pub user_id: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct AdminCommandMessage {
+    pub password: String,
+    pub command: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UsernameMessage {
     pub name: Option<String>,
@@ -7,15 +7,17 @@ use tokio_tungstenite::tungstenite::Message;
 
 use crate::{
     map::{
+        arena::ArenaMap,
         food::FoodData,
         mass_food::{MassFoodInitData, MassFoodUpdateData},
         player::{PlayerInitData, PlayerUpdateData},
         point::Point,
+        power_up::{PowerUp, PowerUpKind},
         virus::VirusData,
     },
     utils::{
         consts::{Mass, TotalMass},
-        id::{FoodID, MassFoodID, PlayerID, VirusID},
+        id::{FoodID, MassFoodID, PlayerID, PowerUpID, VirusID},
     },
 };
 
@@ -37,9 +39,19 @@ pub enum SendEvent {
     GameUpdate,
     FoodsAdded,
     VirusAdded,
-    MassFoodAdded,
     Respawned,
     TransferSol,
+    RoundCountdown,
+    RoundEnded,
+    RoundStarted,
+    ServerClosing,
+    ZoneUpdate,
+    RoundWon,
+    PowerUpsAdded,
+    PowerUpPicked,
+    PlayerTeleported,
+    Spectating,
+    SpawnFailed,
 }
 
 // Notify means that we are going to emit this message globaly
@@ -63,8 +75,18 @@ impl Display for SendEvent {
             SendEvent::GameUpdate => "game_update",
             SendEvent::FoodsAdded => "foods_added",
             SendEvent::VirusAdded => "virus_added",
-            SendEvent::MassFoodAdded => "mass_food_added",
             SendEvent::TransferSol => "transfer",
+            SendEvent::RoundCountdown => "round_countdown",
+            SendEvent::RoundEnded => "round_ended",
+            SendEvent::RoundStarted => "round_started",
+            SendEvent::ServerClosing => "server_closing",
+            SendEvent::ZoneUpdate => "zone_update",
+            SendEvent::RoundWon => "round_won",
+            SendEvent::PowerUpsAdded => "power_ups_added",
+            SendEvent::PowerUpPicked => "power_up_picked",
+            SendEvent::PlayerTeleported => "player_teleported",
+            SendEvent::Spectating => "spectating",
+            SendEvent::SpawnFailed => "spawn_failed",
         })
     }
 }
@@ -80,12 +102,13 @@ impl Into<Event> for SendEvent {
         self.to_string().into()
     }
 }
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct AllInitData {
     pub players: Vec<PlayerInitData>,
     pub virus: Vec<VirusData>,
     pub mass_foods: Vec<MassFoodInitData>,
     pub foods: Vec<FoodData>,
+    pub power_ups: Vec<PowerUp>,
 }
 
 #[derive(Serialize, Clone)]
@@ -93,6 +116,7 @@ pub struct GameUpdateData {
     pub players: Vec<PlayerUpdateData>,
     pub virus: Vec<VirusData>,
     pub mass_food: Vec<MassFoodUpdateData>,
+    pub added_mass: Vec<MassFoodInitData>, // ejected during this tick
     pub removed_foods: Vec<FoodID>,
     pub removed_mass: Vec<MassFoodID>,
     pub removed_virus: Vec<VirusID>
@@ -128,7 +152,8 @@ impl Into<Message> for KickedMessage {
 
 #[derive(Serialize, Clone)]
 pub struct LeaderboardMessage {
-    pub leaderboard: Vec<LeaderboardPlayer>
+    pub leaderboard: Vec<LeaderboardPlayer>,
+    pub teams: Vec<LeaderboardTeam>,
 }
 
 
@@ -139,6 +164,14 @@ pub struct LeaderboardPlayer {
     pub total_won: u64
 }
 
+#[derive(Serialize, Clone)]
+pub struct LeaderboardTeam {
+    pub team: u8,
+    pub hue: u16,
+    pub mass: TotalMass,
+    pub players: usize,
+}
+
 #[derive(Serialize, Clone)]
 pub struct KillMessage {
     pub killed: PlayerID,
@@ -152,7 +185,9 @@ pub struct WelcomeMessage {
     pub default_player_mass: Mass,
     pub default_mass_food: Mass,
     pub default_mass_mass_food: Mass,
-    pub start: u64
+    pub start: u64,
+    pub round_end: Option<i64>,
+    pub map: ArenaMap,
 }
 
 #[derive(Serialize, Clone)]
@@ -189,9 +224,6 @@ pub struct PlayerRespawnedMessage(pub PlayerID);
 #[derive(Serialize, Clone)]
 pub struct RespawnedMessage(pub Point);
 
-#[derive(Serialize, Clone)]
-pub struct MassFoodAddedMessage(pub MassFoodInitData);
-
 #[derive(Serialize, Clone)]
 pub struct VirusAddedMessage {
     pub viruses: Vec<VirusData>
@@ -200,4 +232,77 @@ pub struct VirusAddedMessage {
 #[derive(Serialize, Clone)]
 pub struct FoodAddedMessage {
     pub foods: Vec<FoodData>,
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Clone)]
+pub struct RoundCountdownMessage {
+    pub round: u32,
+    pub seconds_left: i64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RoundEndedMessage {
+    pub round: u32,
+    pub standings: Vec<LeaderboardPlayer>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RoundStartedMessage {
+    pub round: u32,
+    pub start: i64,
+    pub end: i64,
+}
+
+// the spawn was refused, the player watches until the round ends
+#[derive(Serialize, Clone)]
+pub struct SpectatingMessage {
+    pub round: u32,
+    pub round_end: Option<i64>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ServerClosingMessage {
+    pub cashout_window: u64,
+}
+
+// the safe zone of the battle royale, it shrinks linearly from shrink_start to shrink_end
+#[derive(Serialize, Clone)]
+pub struct ZoneMessage {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+    pub final_w: f32,
+    pub final_h: f32,
+    pub shrink_start: i64,
+    pub shrink_end: i64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RoundWonMessage {
+    pub round: u32,
+    pub winner: PlayerID,
+    pub pot: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct PowerUpsAddedMessage {
+    pub power_ups: Vec<PowerUp>,
+}
+
+// the player vanishes at `from` and appears at `to`
+#[derive(Serialize, Clone)]
+pub struct PlayerTeleportedMessage {
+    pub player_id: PlayerID,
+    pub from: Point,
+    pub to: Point,
+    pub charges: u8,
+}
+
+#[derive(Serialize, Clone)]
+pub struct PowerUpPickedMessage {
+    pub id: PowerUpID,
+    pub player_id: PlayerID,
+    pub kind: PowerUpKind,
+    pub until: i64,
+}
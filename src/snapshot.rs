@@ -0,0 +1,154 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{self, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use crate::{
+    map::{food::Food, mass_food::MassFood, virus::Virus},
+    utils::consts::Mass,
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CellSnapshot {
+    pub x: f32,
+    pub y: f32,
+    pub mass: Mass,
+    pub time_to_merge: Option<i64>,
+}
+
+// players are matched back by their matchmaking user id, the PlayerID does not survive a restart
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlayerSnapshot {
+    pub user_id: i64,
+    pub name: Option<String>,
+    pub img_url: Option<String>,
+    pub hue: u16,
+    pub cells: Vec<CellSnapshot>,
+    pub bet: u64,
+    pub total_won: u64,
+    #[serde(default)]
+    pub teleport_charges: u8,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub timestamp: i64,
+    pub foods: Vec<Food>,
+    pub viruses: Vec<Virus>,
+    pub mass_foods: Vec<MassFood>,
+    pub players: Vec<PlayerSnapshot>,
+}
+
+impl WorldSnapshot {
+    // writes to a temporary file first so a crash never leaves a half written snapshot
+    pub async fn save(&self, path: &str) -> anyhow::Result<()> {
+        let path = Path::new(path);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(self)?).await?;
+        fs::rename(&tmp_path, path).await?;
+
+        Ok(())
+    }
+
+    pub async fn load(path: &str) -> anyhow::Result<WorldSnapshot> {
+        let buffer = fs::read(path).await?;
+        Ok(serde_json::from_slice(&buffer)?)
+    }
+
+    pub async fn delete(path: &str) -> anyhow::Result<()> {
+        fs::remove_file(path).await?;
+        Ok(())
+    }
+}
+
+// every balance paid out or lost since the last snapshot, so restoring that snapshot does
+// not pay it a second time. one json line per settlement, appended as soon as it happens
+#[derive(Serialize, Deserialize)]
+struct Settlement {
+    user_id: i64,
+    timestamp: i64,
+}
+
+pub struct SettlementLog {
+    path: PathBuf,
+}
+
+impl SettlementLog {
+    pub fn new(snapshot_path: &str) -> Self {
+        SettlementLog {
+            path: Path::new(snapshot_path).with_extension("settled"),
+        }
+    }
+
+    pub async fn record(&self, user_id: i64, timestamp: i64) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut line = serde_json::to_vec(&Settlement { user_id, timestamp })?;
+        line.push(b'\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+
+        Ok(())
+    }
+
+    // the users settled at or after `timestamp`, an empty set when nothing was recorded
+    pub async fn settled_since(&self, timestamp: i64) -> HashSet<i64> {
+        self.read()
+            .await
+            .into_iter()
+            .filter(|settlement| settlement.timestamp >= timestamp)
+            .map(|settlement| settlement.user_id)
+            .collect()
+    }
+
+    // drops what a snapshot taken at `timestamp` already accounts for
+    pub async fn prune(&self, timestamp: i64) -> anyhow::Result<()> {
+        let mut buffer = vec![];
+        for settlement in self.read().await {
+            if settlement.timestamp >= timestamp {
+                buffer.extend(serde_json::to_vec(&settlement)?);
+                buffer.push(b'\n');
+            }
+        }
+
+        let tmp_path = self.path.with_extension("settled.tmp");
+        fs::write(&tmp_path, buffer).await?;
+        fs::rename(&tmp_path, &self.path).await?;
+
+        Ok(())
+    }
+
+    pub async fn clear(&self) -> anyhow::Result<()> {
+        fs::remove_file(&self.path).await?;
+        Ok(())
+    }
+
+    async fn read(&self) -> Vec<Settlement> {
+        let buffer = fs::read(&self.path).await.unwrap_or_default();
+
+        // a line cut by a crash is skipped, the ones before it still count
+        buffer
+            .split(|byte| *byte == b'\n')
+            .filter_map(|line| serde_json::from_slice(line).ok())
+            .collect()
+    }
+}
@@ -0,0 +1,235 @@
+use crate::map::{
+    player::Player,
+    point::{AsPoint, Point},
+};
+
+use super::{
+    id::PlayerID,
+    quad_tree::{Indexable, QuadTree, Rectangle},
+    rules::EatRules,
+};
+
+// a player cell in the collision index, found back by (player_index, cell_index)
+#[derive(Clone, Copy)]
+struct IndexedCell {
+    player_index: usize,
+    cell_index: usize,
+    position: Point,
+}
+
+impl AsPoint for IndexedCell {
+    fn as_point(&self) -> Point {
+        self.position
+    }
+}
+
+impl Indexable for IndexedCell {
+    type ID = (usize, usize);
+
+    fn get_id(&self) -> (usize, usize) {
+        (self.player_index, self.cell_index)
+    }
+}
+
+// the index covers the map when it wraps, otherwise only where the cells are
+fn create_cell_tree(
+    players: &[&Player],
+    capacity: usize,
+    wrap_size: Option<(f32, f32)>,
+) -> QuadTree<IndexedCell> {
+    if let Some((width, height)) = wrap_size {
+        return QuadTree::new(Rectangle::new(0.0, 0.0, width, height), capacity).wrapping(true);
+    }
+
+    let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+    let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for cell in players.iter().flat_map(|player| player.cells.iter()) {
+        min_x = min_x.min(cell.position.x);
+        min_y = min_y.min(cell.position.y);
+        max_x = max_x.max(cell.position.x);
+        max_y = max_y.max(cell.position.y);
+    }
+
+    if min_x > max_x {
+        return QuadTree::new(Rectangle::new(0.0, 0.0, 0.0, 0.0), capacity);
+    }
+
+    QuadTree::new(
+        Rectangle::new(min_x, min_y, max_x - min_x, max_y - min_y),
+        capacity,
+    )
+}
+
+// returns a list of (player_who_eat, player_eated) - (id, cell_index)
+// in the same order as checking every cell of every pair of players
+pub fn find_who_ate_who(
+    players: &[&Player],
+    rules: &EatRules,
+    node_capacity: usize,
+    wrap_size: Option<(f32, f32)>,
+) -> Vec<((PlayerID, usize), (PlayerID, usize))> {
+    let mut tree = create_cell_tree(players, node_capacity, wrap_size);
+
+    for (player_index, player) in players.iter().enumerate() {
+        for (cell_index, cell) in player.cells.iter().enumerate() {
+            tree.insert(IndexedCell {
+                player_index,
+                cell_index,
+                position: cell.position,
+            });
+        }
+    }
+
+    // (player_a_index, player_b_index, cell_a_index, cell_b_index) with player_a < player_b
+    let mut candidates: Vec<(usize, usize, usize, usize)> = vec![];
+    let mut found = vec![];
+    for (player_index, player) in players.iter().enumerate() {
+        for (cell_index, cell) in player.cells.iter().enumerate() {
+            found.clear();
+            tree.retrieve(&Rectangle::around(&cell.position, 0.0), &mut found);
+
+            for other in found.iter() {
+                if other.player_index > player_index {
+                    candidates.push((player_index, other.player_index, cell_index, other.cell_index));
+                }
+            }
+        }
+    }
+
+    candidates.sort_unstable();
+
+    candidates
+        .into_iter()
+        .filter_map(|(player_a_index, player_b_index, cell_a_index, cell_b_index)| {
+            let player_a = players[player_a_index];
+            let player_b = players[player_b_index];
+
+            // 0: nothing happened
+            // 1: A ate B
+            // 2: B ate A
+            match rules.check_who_ate_who(
+                &player_a.cells[cell_a_index],
+                &player_b.cells[cell_b_index],
+            ) {
+                1 => Some(((player_a.id, cell_a_index), (player_b.id, cell_b_index))),
+                2 => Some(((player_b.id, cell_b_index), (player_a.id, cell_a_index))),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::{
+        map::{cell::Cell, player::Player},
+        utils::{
+            consts::{Mass, COLLISION_NODE_CAPACITY, MIN_SPEED},
+            id::PlayerID,
+            rules::EatRules,
+            util::random_in_range,
+        },
+    };
+
+    use super::find_who_ate_who;
+
+    fn create_players(players_amount: usize, cells_amount: usize, map_size: f32) -> Vec<Player> {
+        (0..players_amount)
+            .map(|i| {
+                let mut player = Player::new(i as PlayerID);
+                let (x, y) = (
+                    random_in_range(0.0..map_size),
+                    random_in_range(0.0..map_size),
+                );
+                for _ in 0..cells_amount {
+                    player.cells.push(Cell::new(
+                        x + random_in_range(-300.0..300.0),
+                        y + random_in_range(-300.0..300.0),
+                        random_in_range(10..2000) as Mass,
+                        MIN_SPEED,
+                        true,
+                        None,
+                        None,
+                    ));
+                }
+                player
+            })
+            .collect()
+    }
+
+    // what Game::get_players_collision used to do, every pair of cells of every pair of players
+    fn brute_force(
+        players: &[&Player],
+        rules: &EatRules,
+    ) -> Vec<((PlayerID, usize), (PlayerID, usize))> {
+        let mut who_ate_who_list = vec![];
+        for a in 0..players.len() {
+            for b in a + 1..players.len() {
+                for (cell_a_index, cell_a) in players[a].cells.iter().enumerate() {
+                    for (cell_b_index, cell_b) in players[b].cells.iter().enumerate() {
+                        match rules.check_who_ate_who(cell_a, cell_b) {
+                            1 => who_ate_who_list.push((
+                                (players[a].id, cell_a_index),
+                                (players[b].id, cell_b_index),
+                            )),
+                            2 => who_ate_who_list.push((
+                                (players[b].id, cell_b_index),
+                                (players[a].id, cell_a_index),
+                            )),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        who_ate_who_list
+    }
+
+    #[test]
+    fn test_find_who_ate_who_same_as_brute_force() {
+        let rules = EatRules::default();
+        for _ in 0..20 {
+            let players = create_players(30, 16, 3_000.0);
+            let players: Vec<&Player> = players.iter().collect();
+
+            let expected = brute_force(&players, &rules);
+            assert!(!expected.is_empty());
+            assert_eq!(find_who_ate_who(&players, &rules, COLLISION_NODE_CAPACITY, None), expected);
+        }
+    }
+
+    // cargo test --release bench_find_who_ate_who -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_find_who_ate_who() {
+        let rules = EatRules::default();
+        let players = create_players(100, 16, 15_000.0);
+        let players: Vec<&Player> = players.iter().collect();
+        let rounds = 50;
+
+        let start = Instant::now();
+        for _ in 0..rounds {
+            brute_force(&players, &rules);
+        }
+        let brute_force_elapsed = start.elapsed() / rounds;
+
+        let start = Instant::now();
+        for _ in 0..rounds {
+            find_who_ate_who(&players, &rules, COLLISION_NODE_CAPACITY, None);
+        }
+        let grid_elapsed = start.elapsed() / rounds;
+
+        println!(
+            "100 players x 16 cells - brute force: {:?} / quad tree: {:?}",
+            brute_force_elapsed, grid_elapsed
+        );
+
+        assert_eq!(
+            find_who_ate_who(&players, &rules, COLLISION_NODE_CAPACITY, None),
+            brute_force(&players, &rules)
+        );
+        assert!(grid_elapsed < brute_force_elapsed);
+    }
+}
@@ -1,8 +1,10 @@
 pub type TotalMass = usize;
-pub type Mass = u16;
+pub type Mass = u32;
 
 pub const MIN_DISTANCE: f32 = 40.0;
 pub const MIN_SPEED: f32 = 9.0;
 pub const PUSHING_AWAY_SPEED: f32 = 2.0;
 pub const MERGE_TIMER: f32 = 20.0;
-pub const SPLIT_CELL_SPEED: f32 = 70.0;
\ No newline at end of file
+pub const SPLIT_CELL_SPEED: f32 = 70.0;
+pub const MAX_SPLITS_PER_INPUT: u8 = 4; // enough for a 16-split
+pub const COLLISION_NODE_CAPACITY: usize = 8;
\ No newline at end of file
@@ -1,3 +1,5 @@
+use crate::{config::get_current_config, map::point::Point};
+
 //make sure that the player is always within the boundaries of the map and limit it there 
 pub fn adjust_for_boundaries(x: &mut f32, y: &mut f32, radius: f32, border_offset: f32, game_width: f32, game_height: f32) {
     let border_calc = radius + border_offset;
@@ -8,3 +10,43 @@ pub fn adjust_for_boundaries(x: &mut f32, y: &mut f32, radius: f32, border_offse
     // Clamp y within the vertical boundaries
     *y = y.clamp(border_calc, game_height - border_calc);
 }
+
+// wraps the position around the edges on a toroidal arena, otherwise keeps it inside the borders
+pub fn keep_in_arena(x: &mut f32, y: &mut f32, radius: f32, border_offset: f32, game_width: f32, game_height: f32) {
+    if get_current_config().arena.wrap {
+        *x = x.rem_euclid(game_width);
+        *y = y.rem_euclid(game_height);
+    } else {
+        adjust_for_boundaries(x, y, radius, border_offset, game_width, game_height);
+    }
+}
+
+// shortest offset from `from` to `to`, through the edges when the arena wraps around (width, height)
+pub fn wrapped_delta(from: &Point, to: &Point, wrap_size: Option<(f32, f32)>) -> (f32, f32) {
+    let (mut dx, mut dy) = (to.x - from.x, to.y - from.y);
+
+    if let Some((width, height)) = wrap_size {
+        dx -= width * (dx / width).round();
+        dy -= height * (dy / height).round();
+    }
+
+    (dx, dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::point::Point;
+
+    use super::wrapped_delta;
+
+    #[test]
+    fn test_wrapped_delta_takes_the_shortest_way() {
+        let point = |x, y| Point { x, y, radius: 0.0 };
+        let (a, b) = (point(10.0, 500.0), point(990.0, 20.0));
+
+        assert_eq!(wrapped_delta(&a, &b, None), (980.0, -480.0));
+        assert_eq!(wrapped_delta(&a, &b, Some((1_000.0, 1_000.0))), (-20.0, -480.0));
+        assert_eq!(wrapped_delta(&b, &a, Some((1_000.0, 1_000.0))), (20.0, 480.0));
+        assert_eq!(wrapped_delta(&a, &b, Some((1_000.0, 600.0))), (-20.0, 120.0));
+    }
+}
@@ -6,3 +6,4 @@ pub type PlayerID = u8;
 pub type FoodID = u32;
 pub type VirusID = u16;
 pub type MassFoodID = u16;
+pub type PowerUpID = u16;
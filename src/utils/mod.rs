@@ -4,4 +4,7 @@ pub mod quad_tree;
 pub mod consts;
 pub mod queue_message;
 pub mod id;
-pub mod amount_queue;
\ No newline at end of file
+pub mod amount_queue;
+pub mod collision;
+pub mod rules;
+pub mod spawn;
\ No newline at end of file
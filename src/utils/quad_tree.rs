@@ -1,8 +1,13 @@
-use crate::map::food::Food;
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
 
-use super::id::FoodID;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+use crate::map::point::{AsPoint, Point};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Rectangle {
     pub x: f32,
     pub y: f32,
@@ -15,36 +20,186 @@ impl Rectangle {
         Rectangle { x, y, w, h }
     }
 
-    fn contains(&self, point: &Food) -> bool {
+    // square bounding box of a circle, grown by margin on every side
+    pub fn around(point: &Point, margin: f32) -> Self {
+        let half_size = point.radius + margin;
+        Rectangle::new(
+            point.x - half_size,
+            point.y - half_size,
+            half_size * 2.0,
+            half_size * 2.0,
+        )
+    }
+
+    pub fn expand(&self, margin: f32) -> Self {
+        Rectangle::new(
+            self.x - margin,
+            self.y - margin,
+            self.w + margin * 2.0,
+            self.h + margin * 2.0,
+        )
+    }
+
+    pub fn translate(&self, dx: f32, dy: f32) -> Self {
+        Rectangle::new(self.x + dx, self.y + dy, self.w, self.h)
+    }
+
+    pub fn intersection(&self, other: &Rectangle) -> Option<Rectangle> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let w = (self.x + self.w).min(other.x + other.w) - x;
+        let h = (self.y + self.h).min(other.y + other.h) - y;
+
+        (w > 0.0 && h > 0.0).then(|| Rectangle::new(x, y, w, h))
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
         let in_x_bounds = point.x >= self.x && point.x <= (self.x + self.w);
         let in_y_bounds = point.y >= self.y && point.y <= (self.y + self.h);
         in_x_bounds && in_y_bounds
     }
 
-    fn intersects(&self, range: &Rectangle) -> bool {
+    // true if the circle overlaps the rectangle, not only its center
+    fn intersects_circle(&self, point: &Point) -> bool {
+        let dx = point.x - point.x.clamp(self.x, self.x + self.w);
+        let dy = point.y - point.y.clamp(self.y, self.y + self.h);
+        dx * dx + dy * dy <= point.radius * point.radius
+    }
+
+    pub fn intersects(&self, range: &Rectangle) -> bool {
         let x_overlap = !(range.x > self.x + self.w || range.x + range.w < self.x);
         let y_overlap = !(range.y > self.y + self.h || range.y + range.h < self.y);
         x_overlap && y_overlap
     }
 }
 
-//This is an implementation of a quadtree which helps optimizing the food.rs so that the search for the food on the screen is made fast
+const MIN_NODE_SIZE: f32 = 1.0;
+
+// Anything that can live in the QuadTree, the ID is used to find it back on removal
+pub trait Indexable: AsPoint + Clone {
+    type ID: Copy + Eq + Hash;
+
+    fn get_id(&self) -> Self::ID;
+}
+
+//This is an implementation of a quadtree which helps optimizing the search for the entities on the screen
 //reducing the complexity from O^2 to n log(n)
-//I used it only on the food because there is a lot of food compared to other items
-pub struct QuadTree {
+//It is used for the food, the viruses, the mass food and the player cells in the collision check
+pub struct QuadTree<T: Indexable> {
+    root: QuadNode<T>,
+    // where every item was inserted, an item is found back by walking straight to its node
+    positions: HashMap<T::ID, Vec<Point>>,
+    // biggest radius ever inserted, used to grow the search area of radius aware queries
+    max_radius: f32,
+    // toroidal arena, a range going past an edge also looks on the opposite side
+    wrap: bool,
+}
+
+impl<T: Indexable> QuadTree<T> {
+    pub fn new(boundary: Rectangle, capacity: usize) -> Self {
+        QuadTree {
+            root: QuadNode::new(boundary, capacity),
+            positions: HashMap::new(),
+            max_radius: 0.0,
+            wrap: false,
+        }
+    }
+
+    pub fn wrapping(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn clear(&mut self) {
+        self.root.clear();
+        self.positions.clear();
+        self.max_radius = 0.0;
+    }
+
+    pub fn get_all(&self) -> Vec<&T> {
+        self.root.get_all()
+    }
+
+    pub fn insert(&mut self, item: T) -> bool {
+        let id = item.get_id();
+        let position = item.as_point();
+
+        if !self.root.insert(item) {
+            return false;
+        }
+
+        self.positions.entry(id).or_default().push(position);
+        self.max_radius = self.max_radius.max(position.radius);
+        true
+    }
+
+    pub fn contains(&self, id: T::ID) -> bool {
+        self.positions.contains_key(&id)
+    }
+
+    pub fn remove(&mut self, item: &T) -> bool {
+        self.remove_by_id(item.get_id()).is_some()
+    }
+
+    pub fn remove_by_id(&mut self, id: T::ID) -> Option<T> {
+        let positions = self.positions.get_mut(&id)?;
+        let position = positions.pop()?;
+
+        if positions.is_empty() {
+            self.positions.remove(&id);
+        }
+
+        self.root.remove(&position, id)
+    }
+
+    // items whose circle overlaps the range, so nothing pops in at the edge of the view
+    pub fn retrieve(&self, range: &Rectangle, found: &mut Vec<T>) {
+        let search_area = range.expand(self.max_radius);
+        if !self.wrap {
+            self.root.retrieve(&search_area, range, found);
+            return;
+        }
+
+        // the range moved by a map size is the same area seen through an edge
+        let bounds = self.root.boundary;
+        let from = found.len();
+        for dx in [-bounds.w, 0.0, bounds.w] {
+            for dy in [-bounds.h, 0.0, bounds.h] {
+                let shifted_area = search_area.translate(dx, dy);
+                if bounds.intersects(&shifted_area) {
+                    self.root
+                        .retrieve(&shifted_area, &range.translate(dx, dy), found);
+                }
+            }
+        }
+
+        // a range wider than the map can see the same item more than once
+        let mut seen = HashSet::new();
+        let mut index = from;
+        while index < found.len() {
+            if seen.insert(found[index].get_id()) {
+                index += 1;
+            } else {
+                found.swap_remove(index);
+            }
+        }
+    }
+}
+
+struct QuadNode<T: Indexable> {
     boundary: Rectangle,
     capacity: usize,
-    points: Vec<Food>,
+    points: Vec<T>,
     divided: bool,
-    north_west: Option<Box<QuadTree>>,
-    north_east: Option<Box<QuadTree>>,
-    south_west: Option<Box<QuadTree>>,
-    south_east: Option<Box<QuadTree>>,
+    north_west: Option<Box<QuadNode<T>>>,
+    north_east: Option<Box<QuadNode<T>>>,
+    south_west: Option<Box<QuadNode<T>>>,
+    south_east: Option<Box<QuadNode<T>>>,
 }
 
-impl QuadTree {
-    pub fn new(boundary: Rectangle, capacity: usize) -> Self {
-        QuadTree {
+impl<T: Indexable> QuadNode<T> {
+    fn new(boundary: Rectangle, capacity: usize) -> Self {
+        QuadNode {
             boundary,
             capacity,
             points: Vec::new(),
@@ -56,33 +211,48 @@ impl QuadTree {
         }
     }
 
-    pub fn get_all_foods(&self) -> Vec<&Food> {
-        let mut foods: Vec<&Food> = self.points.iter().collect();
+    fn clear(&mut self) {
+        self.points.clear();
+        self.divided = false;
+        self.north_west = None;
+        self.north_east = None;
+        self.south_west = None;
+        self.south_east = None;
+    }
+
+    fn get_all(&self) -> Vec<&T> {
+        let mut items: Vec<&T> = self.points.iter().collect();
 
         if self.divided {
             if let Some(ref node) = self.north_west {
-                foods.extend(node.get_all_foods());
+                items.extend(node.get_all());
             }
             if let Some(ref node) = self.north_east {
-                foods.extend(node.get_all_foods());
+                items.extend(node.get_all());
             }
             if let Some(ref node) = self.south_west {
-                foods.extend(node.get_all_foods());
+                items.extend(node.get_all());
             }
             if let Some(ref node) = self.south_east {
-                foods.extend(node.get_all_foods());
+                items.extend(node.get_all());
             }
         }
 
-        foods
+        items
     }
 
-    pub fn insert(&mut self, point: Food) -> bool {
-        if !self.boundary.contains(&point) {
+    fn insert(&mut self, point: T) -> bool {
+        let position = point.as_point();
+
+        if !self.boundary.contains(&position) {
             return false;
         }
 
-        if self.points.len() < self.capacity {
+        // a node too small to divide takes everything, items on the same spot would split forever
+        if self.points.len() < self.capacity
+            || self.boundary.w < MIN_NODE_SIZE
+            || self.boundary.h < MIN_NODE_SIZE
+        {
             self.points.push(point);
             return true;
         } else {
@@ -92,29 +262,31 @@ impl QuadTree {
 
             if self.divided {
                 if let Some(ref mut node) = self.north_west {
-                    if node.insert(point) {
-                        return true;
+                    if node.boundary.contains(&position) {
+                        return node.insert(point);
                     }
                 }
                 if let Some(ref mut node) = self.north_east {
-                    if node.insert(point) {
-                        return true;
+                    if node.boundary.contains(&position) {
+                        return node.insert(point);
                     }
                 }
                 if let Some(ref mut node) = self.south_west {
-                    if node.insert(point) {
-                        return true;
+                    if node.boundary.contains(&position) {
+                        return node.insert(point);
                     }
                 }
                 if let Some(ref mut node) = self.south_east {
-                    if node.insert(point) {
-                        return true;
+                    if node.boundary.contains(&position) {
+                        return node.insert(point);
                     }
                 }
             }
         }
 
-        false
+        // on the far edge the children can miss it by a rounding error, it stays here
+        self.points.push(point);
+        true
     }
 
     fn subdivide(&mut self) {
@@ -123,19 +295,19 @@ impl QuadTree {
         let w = self.boundary.w / 2.0;
         let h = self.boundary.h / 2.0;
 
-        self.north_west = Some(Box::new(QuadTree::new(
+        self.north_west = Some(Box::new(QuadNode::new(
             Rectangle { x, y, w, h },
             self.capacity,
         )));
-        self.north_east = Some(Box::new(QuadTree::new(
+        self.north_east = Some(Box::new(QuadNode::new(
             Rectangle { x: x + w, y, w, h },
             self.capacity,
         )));
-        self.south_west = Some(Box::new(QuadTree::new(
+        self.south_west = Some(Box::new(QuadNode::new(
             Rectangle { x, y: y + h, w, h },
             self.capacity,
         )));
-        self.south_east = Some(Box::new(QuadTree::new(
+        self.south_east = Some(Box::new(QuadNode::new(
             Rectangle {
                 x: x + w,
                 y: y + h,
@@ -148,100 +320,120 @@ impl QuadTree {
         self.divided = true;
     }
 
-    pub fn retrieve(&self, range: &Rectangle, found: &mut Vec<Food>) {
-        if !self.boundary.intersects(range) {
+    // pulls the children back in once they are leaves and all their points fit in this node
+    fn merge_if_underfull(&mut self) {
+        if !self.divided {
             return;
         }
 
-        for p in &self.points {
-            if range.contains(p) {
-                found.push(*p);
-            }
+        let children = [
+            &self.north_west,
+            &self.north_east,
+            &self.south_west,
+            &self.south_east,
+        ];
+
+        if children.iter().flat_map(|c| c.iter()).any(|node| node.divided) {
+            return;
         }
 
-        if self.divided {
-            if let Some(ref node) = self.north_west {
-                node.retrieve(range, found);
-            }
-            if let Some(ref node) = self.north_east {
-                node.retrieve(range, found);
-            }
-            if let Some(ref node) = self.south_west {
-                node.retrieve(range, found);
-            }
-            if let Some(ref node) = self.south_east {
-                node.retrieve(range, found);
-            }
+        let children_len: usize = children
+            .iter()
+            .flat_map(|c| c.iter())
+            .map(|node| node.points.len())
+            .sum();
+
+        if self.points.len() + children_len > self.capacity {
+            return;
+        }
+
+        for node in [
+            self.north_west.take(),
+            self.north_east.take(),
+            self.south_west.take(),
+            self.south_east.take(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            self.points.extend(node.points);
         }
+
+        self.divided = false;
     }
 
-    pub fn contains_food(&self, food_id: FoodID) -> bool {
-        if let Some(_) = self.points.iter().position(|p| p.id == food_id) {
-            return true;
+    // search_area is the range grown by the biggest radius, a node outside of it can't hold
+    // any circle touching the range
+    fn retrieve(&self, search_area: &Rectangle, range: &Rectangle, found: &mut Vec<T>) {
+        if !self.boundary.intersects(search_area) {
+            return;
+        }
+
+        for p in &self.points {
+            if range.intersects_circle(&p.as_point()) {
+                found.push(p.clone());
+            }
         }
 
         if self.divided {
             if let Some(ref node) = self.north_west {
-                if node.contains_food(food_id) {
-                    return true;
-                }
+                node.retrieve(search_area, range, found);
             }
             if let Some(ref node) = self.north_east {
-                if node.contains_food(food_id) {
-                    return true;
-                }
+                node.retrieve(search_area, range, found);
             }
             if let Some(ref node) = self.south_west {
-                if node.contains_food(food_id) {
-                    return true;
-                }
+                node.retrieve(search_area, range, found);
             }
             if let Some(ref node) = self.south_east {
-                if node.contains_food(food_id) {
-                    return true;
-                }
+                node.retrieve(search_area, range, found);
             }
         }
-
-        false
     }
 
-    pub fn remove(&mut self, food: &Food) -> bool {
-        if !self.boundary.contains(food) {
-            return false;
+    fn remove(&mut self, position: &Point, id: T::ID) -> Option<T> {
+        if !self.boundary.contains(position) {
+            return None;
         }
 
         // Try to remove the point from the current node
-        if let Some(index) = self.points.iter().position(|p| p.id == food.id) {
-            self.points.remove(index);
-            return true;
+        if let Some(index) = self.points.iter().position(|p| p.get_id() == id) {
+            let removed = self.points.remove(index);
+            self.merge_if_underfull();
+            return Some(removed);
         }
 
         // If the point is not in the current node and the tree is divided, try to remove it from the children
         if self.divided {
+            let mut removed = None;
+
             if let Some(ref mut node) = self.north_west {
-                if node.remove(food) {
-                    return true;
-                }
+                removed = node.remove(position, id);
             }
-            if let Some(ref mut node) = self.north_east {
-                if node.remove(food) {
-                    return true;
+            if removed.is_none() {
+                if let Some(ref mut node) = self.north_east {
+                    removed = node.remove(position, id);
                 }
             }
-            if let Some(ref mut node) = self.south_west {
-                if node.remove(food) {
-                    return true;
+            if removed.is_none() {
+                if let Some(ref mut node) = self.south_west {
+                    removed = node.remove(position, id);
                 }
             }
-            if let Some(ref mut node) = self.south_east {
-                if node.remove(food) {
-                    return true;
+            if removed.is_none() {
+                if let Some(ref mut node) = self.south_east {
+                    removed = node.remove(position, id);
                 }
             }
+
+            if removed.is_some() {
+                self.merge_if_underfull();
+            }
+
+            return removed;
         }
 
-        false
+        None
     }
 }
 
@@ -264,11 +456,12 @@ mod tests {
                     y: 0.0,
                     radius: 1.0,
                 },
+                &mut rand::thread_rng(),
             ));
         }
 
-        assert_eq!(quad_tree.points.len(), 25);
-        assert_eq!(quad_tree.divided, false);
+        assert_eq!(quad_tree.root.points.len(), 25);
+        assert_eq!(quad_tree.root.divided, false);
 
         quad_tree.insert(Food::new(
             9999 as u32,
@@ -277,12 +470,13 @@ mod tests {
                 y: 0.0,
                 radius: 1.0,
             },
+            &mut rand::thread_rng(),
         ));
 
-        assert_eq!(quad_tree.points.len(), 25);
-        assert_eq!(quad_tree.divided, true);
+        assert_eq!(quad_tree.root.points.len(), 25);
+        assert_eq!(quad_tree.root.divided, true);
 
-        match quad_tree.north_west {
+        match quad_tree.root.north_west {
             Some(ref tree) => {
                 assert_eq!(tree.points.len(), 1);
                 assert_eq!(tree.divided, false);
@@ -298,10 +492,11 @@ mod tests {
                     y: 0.0,
                     radius: 1.0,
                 },
+                &mut rand::thread_rng(),
             ));
         }
 
-        match quad_tree.north_west {
+        match quad_tree.root.north_west {
             Some(ref tree) => match tree.north_west {
                 Some(ref tree_north) => {
                     assert_eq!(tree_north.points.len(), 1);
@@ -313,6 +508,83 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_quad_tree_contains_by_id() {
+        let boundary = Rectangle::new(0.0, 0.0, 6_000.0, 6_000.0);
+        let mut quad_tree = QuadTree::new(boundary, 4);
+
+        for i in 0..20 {
+            quad_tree.insert(Food::new(
+                i as u32,
+                &Point {
+                    x: (i * 250) as f32,
+                    y: (i * 250) as f32,
+                    radius: 1.0,
+                },
+                &mut rand::thread_rng(),
+            ));
+        }
+
+        assert!(quad_tree.contains(0));
+        assert!(quad_tree.contains(19));
+        assert!(!quad_tree.contains(20));
+
+        let removed = quad_tree.remove_by_id(19).unwrap();
+        assert_eq!(removed.id, 19);
+        assert_eq!(removed.x, 4_750.0);
+        assert!(!quad_tree.contains(19));
+        assert!(quad_tree.remove_by_id(19).is_none());
+        assert_eq!(quad_tree.get_all().len(), 19);
+    }
+
+    #[test]
+    fn test_quad_tree_collapse() {
+        let boundary = Rectangle::new(0.0, 0.0, 6_000.0, 6_000.0);
+        let mut quad_tree = QuadTree::new(boundary, 25);
+
+        for i in 0..60 {
+            quad_tree.insert(Food::new(
+                i as u32,
+                &Point {
+                    x: 0.0,
+                    y: 0.0,
+                    radius: 1.0,
+                },
+                &mut rand::thread_rng(),
+            ));
+        }
+
+        assert!(quad_tree.root.divided);
+        match quad_tree.root.north_west {
+            Some(ref tree) => assert!(tree.divided),
+            None => panic!(),
+        }
+
+        // root holds 0..25, north_west 25..50 and its own north_west 50..60
+        for i in 25..35 {
+            assert!(quad_tree.remove_by_id(i).is_some());
+        }
+
+        // north_west merged back its child, the root is still over capacity
+        assert!(quad_tree.root.divided);
+        match quad_tree.root.north_west {
+            Some(ref tree) => {
+                assert!(!tree.divided);
+                assert_eq!(tree.points.len(), 25);
+            }
+            None => panic!(),
+        }
+
+        for i in 0..25 {
+            assert!(quad_tree.remove_by_id(i).is_some());
+        }
+
+        assert!(!quad_tree.root.divided);
+        assert!(quad_tree.root.north_west.is_none());
+        assert_eq!(quad_tree.root.points.len(), 25);
+        assert_eq!(quad_tree.get_all().len(), 25);
+    }
+
     #[test]
     fn test_quad_tree_retrieve() {
         let boundary = Rectangle::new(0.0, 0.0, 6_000.0, 6_000.0);
@@ -326,6 +598,7 @@ mod tests {
                     y: 0.0,
                     radius: 1.0,
                 },
+                &mut rand::thread_rng(),
             ));
         }
 
@@ -335,10 +608,43 @@ mod tests {
         quad_tree.retrieve(&player_view, &mut foods);
         assert_eq!(foods.len(), 26);
 
+        // the food centers are outside of the view but their radius reaches inside of it
         let player_view = Rectangle::new(2.0, 2.0, 1920.0, 1080.0);
 
+        let mut foods = vec![];
+        quad_tree.retrieve(&player_view, &mut foods);
+        assert_eq!(foods.len(), 26);
+
+        let player_view = Rectangle::new(20.0, 20.0, 1920.0, 1080.0);
+
         let mut foods = vec![];
         quad_tree.retrieve(&player_view, &mut foods);
         assert_eq!(foods.len(), 0);
     }
+
+    #[test]
+    fn test_quad_tree_retrieve_wraps_around() {
+        let boundary = Rectangle::new(0.0, 0.0, 1_000.0, 1_000.0);
+        let mut quad_tree = QuadTree::new(boundary, 4).wrapping(true);
+
+        let food = |id, x, y| Food::new(id, &Point { x, y, radius: 1.0 }, &mut rand::thread_rng());
+        quad_tree.insert(food(0, 5.0, 500.0));
+        quad_tree.insert(food(1, 995.0, 995.0));
+        quad_tree.insert(food(2, 500.0, 500.0));
+
+        // past the right edge, sees the left side of the map
+        let mut foods = vec![];
+        quad_tree.retrieve(&Rectangle::new(950.0, 450.0, 100.0, 100.0), &mut foods);
+        assert_eq!(foods.iter().map(|f| f.id).collect::<Vec<_>>(), vec![0]);
+
+        // through the top left corner
+        let mut foods = vec![];
+        quad_tree.retrieve(&Rectangle::new(-50.0, -50.0, 100.0, 100.0), &mut foods);
+        assert_eq!(foods.iter().map(|f| f.id).collect::<Vec<_>>(), vec![1]);
+
+        // wider than the map, every food only once
+        let mut foods = vec![];
+        quad_tree.retrieve(&Rectangle::new(-100.0, -100.0, 1_500.0, 1_500.0), &mut foods);
+        assert_eq!(foods.len(), 3);
+    }
 }
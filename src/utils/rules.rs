@@ -0,0 +1,226 @@
+use std::f32::consts::PI;
+
+use crate::map::{cell::Cell, point::Point};
+
+use super::consts::Mass;
+
+#[derive(Debug, Clone, Copy)]
+pub struct EatRule {
+    // the eater needs more than mass_ratio times the mass of what it eats
+    pub mass_ratio: f32,
+    // part of the smaller circle that has to be covered, 0.0 means touching is enough
+    pub overlap: f32,
+}
+
+impl EatRule {
+    pub fn can_eat(
+        &self,
+        eater_mass: Mass,
+        eater_position: &Point,
+        eaten_mass: Mass,
+        eaten_position: &Point,
+    ) -> bool {
+        (eater_mass as f32) > (eaten_mass as f32) * self.mass_ratio
+            && check_overlap(eater_position, eaten_position, self.overlap)
+    }
+}
+
+#[derive(Debug)]
+pub struct EatRules {
+    pub cell: EatRule,
+    pub virus: EatRule,
+    pub mass_food: EatRule,
+    // overlap two cells of the same player need before merging
+    pub merge_overlap: f32,
+}
+
+impl Default for EatRules {
+    fn default() -> Self {
+        EatRules {
+            cell: EatRule {
+                mass_ratio: 1.2,
+                overlap: 0.6,
+            },
+            virus: EatRule {
+                mass_ratio: 1.1,
+                overlap: 0.6,
+            },
+            mass_food: EatRule {
+                mass_ratio: 1.1,
+                overlap: 0.0,
+            },
+            merge_overlap: 0.6,
+        }
+    }
+}
+
+impl EatRules {
+    //checks which cell ate the other one, the same rule is used in both directions
+    // 0: nothing happened
+    // 1: A ate B
+    // 2: B ate A
+    pub fn check_who_ate_who(&self, cell_a: &Cell, cell_b: &Cell) -> u8 {
+        if self
+            .cell
+            .can_eat(cell_a.mass, &cell_a.position, cell_b.mass, &cell_b.position)
+        {
+            return 1;
+        }
+
+        if self
+            .cell
+            .can_eat(cell_b.mass, &cell_b.position, cell_a.mass, &cell_a.position)
+        {
+            return 2;
+        }
+
+        0
+    }
+}
+
+//returns true if the intersection covers at least `fraction` of the smaller circle
+pub fn check_overlap(circle_a: &Point, circle_b: &Point, fraction: f32) -> bool {
+    let distance = circle_a.distance(circle_b);
+
+    let r1 = circle_a.radius;
+    let r2 = circle_b.radius;
+    let r_min = r1.min(r2);
+    let r_max = r1.max(r2);
+
+    // Check for complete containment
+    if distance + r_min <= r_max {
+        return true; // One circle is completely inside the other
+    }
+
+    // Calculate intersection area if circles are partially overlapping
+    if distance < r1 + r2 && distance > f32::abs(r1 - r2) {
+        let angle1 = f32::acos((distance * distance + r1 * r1 - r2 * r2) / (2.0 * distance * r1));
+        let angle2 = f32::acos((distance * distance + r2 * r2 - r1 * r1) / (2.0 * distance * r2));
+        let part1 = r1 * r1 * angle1;
+        let part2 = r2 * r2 * angle2;
+        let part3 = 0.5
+            * f32::sqrt(
+                (-distance + r1 + r2)
+                    * (distance + r1 - r2)
+                    * (distance - r1 + r2)
+                    * (distance + r1 + r2),
+            );
+        let intersection_area = part1 + part2 - part3;
+
+        // Check if the intersection area is at least the fraction of the area of the smaller circle
+        if intersection_area >= fraction * PI * r_min * r_min {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        map::{cell::Cell, point::Point},
+        utils::{
+            consts::{Mass, MIN_SPEED},
+            util::mass_to_radius,
+        },
+    };
+
+    use super::{check_overlap, EatRule, EatRules};
+
+    fn point(x: f32, radius: f32) -> Point {
+        Point { x, y: 0.0, radius }
+    }
+
+    fn cell(x: f32, mass: Mass) -> Cell {
+        Cell::new(x, 0.0, mass, MIN_SPEED, true, None, None)
+    }
+
+    #[test]
+    fn test_check_overlap() {
+        // (circle_a, circle_b, fraction, expected)
+        let cases = [
+            (point(0.0, 10.0), point(0.0, 5.0), 1.0, true), // contained
+            (point(0.0, 10.0), point(30.0, 10.0), 0.0, false), // apart
+            (point(0.0, 10.0), point(19.0, 10.0), 0.0, true), // touching
+            (point(0.0, 10.0), point(19.0, 10.0), 0.6, false),
+            (point(0.0, 10.0), point(5.0, 10.0), 0.6, true),
+            (point(0.0, 10.0), point(5.0, 10.0), 0.7, false),
+            (point(0.0, 50.0), point(45.0, 10.0), 0.6, true),
+            (point(0.0, 50.0), point(55.0, 10.0), 0.6, false),
+        ];
+
+        for (i, (circle_a, circle_b, fraction, expected)) in cases.iter().enumerate() {
+            assert_eq!(
+                check_overlap(circle_a, circle_b, *fraction),
+                *expected,
+                "case {}",
+                i
+            );
+            assert_eq!(
+                check_overlap(circle_b, circle_a, *fraction),
+                *expected,
+                "case {} reversed",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_who_ate_who() {
+        let rules = EatRules::default();
+
+        // (cell_a, cell_b, expected)
+        let cases = [
+            (cell(0.0, 100), cell(0.0, 100), 0), // same mass
+            (cell(0.0, 120), cell(0.0, 100), 0), // exactly the ratio is not enough
+            (cell(0.0, 121), cell(0.0, 100), 1),
+            (cell(0.0, 100), cell(0.0, 121), 2), // same threshold in both directions
+            (cell(0.0, 500), cell(2_000.0, 10), 0), // too far
+            (cell(0.0, 500), cell(110.0, 100), 1),
+            (cell(0.0, 500), cell(130.0, 100), 0), // not covered enough
+        ];
+
+        for (i, (cell_a, cell_b, expected)) in cases.iter().enumerate() {
+            assert_eq!(
+                rules.check_who_ate_who(cell_a, cell_b),
+                *expected,
+                "case {}",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_eat_rule_can_eat() {
+        let rules = EatRules::default();
+        let custom = EatRule {
+            mass_ratio: 2.0,
+            overlap: 0.0,
+        };
+
+        // (rule, eater_mass, eaten_mass, distance, expected)
+        let cases = [
+            (rules.virus, 111, 100, 0.0, true),
+            (rules.virus, 110, 100, 0.0, false),
+            (rules.virus, 500, 100, 150.0, false), // only touching the virus
+            (rules.mass_food, 23, 20, 0.0, true),
+            (rules.mass_food, 22, 20, 0.0, false),
+            (rules.mass_food, 100, 20, 80.0, true), // touching is enough
+            (custom, 201, 100, 0.0, true),
+            (custom, 200, 100, 0.0, false),
+        ];
+
+        for (i, (rule, eater_mass, eaten_mass, distance, expected)) in cases.iter().enumerate() {
+            let eater = point(0.0, mass_to_radius(*eater_mass));
+            let eaten = point(*distance, mass_to_radius(*eaten_mass));
+
+            assert_eq!(
+                rule.can_eat(*eater_mass, &eater, *eaten_mass, &eaten),
+                *expected,
+                "case {}",
+                i
+            );
+        }
+    }
+}
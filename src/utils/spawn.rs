@@ -0,0 +1,141 @@
+use crate::{config::get_current_config, map::point::Point};
+
+use super::{game_logic::adjust_for_boundaries, quad_tree::Rectangle, util::mass_to_radius};
+
+// random spots compared by the farthest strategy
+const SPAWN_CANDIDATES: usize = 10;
+// random spots tried when the preferred ones are all under a bigger cell
+const SAFE_ATTEMPTS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpawnStrategy {
+    Farthest, // away from the other cells, the bigger they are the farther
+    FoodRich, // where the food is the densest
+    Zone,     // anywhere in the spawn zones of the map
+    MassBlob, // on the biggest pile of ejected mass
+}
+
+impl SpawnStrategy {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "food" => SpawnStrategy::FoodRich,
+            "zone" => SpawnStrategy::Zone,
+            "mass" => SpawnStrategy::MassBlob,
+            _ => SpawnStrategy::Farthest,
+        }
+    }
+}
+
+// what the strategies look at, gathered before any player is locked
+#[derive(Debug, Default)]
+pub struct SpawnContext {
+    pub cells: Vec<Point>,      // every cell in game, the radius grows with the mass
+    pub food_spots: Vec<Point>, // richest first, only filled for FoodRich
+    pub blobs: Vec<Point>,      // biggest first, only filled for MassBlob
+}
+
+// a spawn point inside `area` following the configured strategy, never under a bigger cell.
+// None when every spot tried is taken, the caller tries again later
+pub fn spawn_position(context: &SpawnContext, area: &Rectangle) -> Option<Point> {
+    let config = get_current_config();
+    let radius = mass_to_radius(config.default_player_mass);
+    let random_point = || place(config.arena.random_spawn_point(radius), area);
+
+    let preferred = match config.spawn.strategy {
+        SpawnStrategy::Farthest => vec![farthest_from_cells(&context.cells, random_point)],
+        SpawnStrategy::FoodRich => context.food_spots.clone(),
+        SpawnStrategy::MassBlob => context.blobs.clone(),
+        SpawnStrategy::Zone => vec![],
+    };
+
+    preferred
+        .into_iter()
+        .map(|point| place(Point { radius, ..point }, area))
+        .chain((0..SAFE_ATTEMPTS).map(|_| random_point()))
+        .find(|point| is_safe(point, &context.cells))
+}
+
+// moved inside the area first, an obstacle then has the last word
+fn place(point: Point, area: &Rectangle) -> Point {
+    let mut point = keep_inside(point, area);
+    get_current_config().arena.push_out_of_obstacles(&mut point);
+    point
+}
+
+fn keep_inside(mut point: Point, area: &Rectangle) -> Point {
+    let (mut x, mut y) = (point.x - area.x, point.y - area.y);
+    adjust_for_boundaries(&mut x, &mut y, point.radius, 0.0, area.w, area.h);
+    point.x = area.x + x;
+    point.y = area.y + y;
+    point
+}
+
+// false if a bigger cell covers any part of the point
+fn is_safe(point: &Point, cells: &[Point]) -> bool {
+    cells.iter().all(|cell| {
+        cell.radius <= point.radius || point.distance(cell) >= cell.radius + point.radius
+    })
+}
+
+// the candidate with the most room, the room around a cell is counted in its radius so
+// a big cell pushes the spawn farther than a small one
+fn farthest_from_cells(cells: &[Point], create_candidate: impl Fn() -> Point) -> Point {
+    let room = |candidate: &Point| {
+        cells
+            .iter()
+            .map(|cell| (candidate.distance(cell) - cell.radius) / cell.radius.max(1.0))
+            .fold(f32::INFINITY, f32::min)
+    };
+
+    (0..SPAWN_CANDIDATES)
+        .map(|_| create_candidate())
+        .max_by(|a, b| room(a).total_cmp(&room(b)))
+        .unwrap_or_else(create_candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::point::Point;
+
+    use super::{
+        farthest_from_cells, is_safe, keep_inside, spawn_position, SpawnContext, SpawnStrategy,
+    };
+    use crate::utils::quad_tree::Rectangle;
+
+    fn circle(x: f32, y: f32, radius: f32) -> Point {
+        Point { x, y, radius }
+    }
+
+    #[test]
+    fn test_spawn_avoids_bigger_cells() {
+        let cells = [circle(500.0, 500.0, 100.0), circle(100.0, 100.0, 10.0)];
+
+        assert!(!is_safe(&circle(580.0, 500.0, 20.0), &cells));
+        assert!(is_safe(&circle(640.0, 500.0, 20.0), &cells));
+        // smaller cells can't eat the new player
+        assert!(is_safe(&circle(100.0, 100.0, 20.0), &cells));
+
+        // both are 200 away from a cell, next to the big one there is less room
+        let candidates = [circle(300.0, 500.0, 20.0), circle(100.0, 300.0, 20.0)];
+        let next = std::cell::Cell::new(0);
+        let picked = farthest_from_cells(&cells, || {
+            next.set(next.get() + 1);
+            candidates[next.get() % 2]
+        });
+        assert_eq!((picked.x, picked.y), (100.0, 300.0));
+
+        let area = Rectangle::new(1_000.0, 1_000.0, 500.0, 500.0);
+        let inside = keep_inside(circle(0.0, 1_200.0, 20.0), &area);
+        assert_eq!((inside.x, inside.y), (1_020.0, 1_200.0));
+
+        // no room anywhere, no spawn rather than under a bigger cell
+        let covered = SpawnContext {
+            cells: vec![circle(1_250.0, 1_250.0, 1_000.0)],
+            ..Default::default()
+        };
+        assert!(spawn_position(&covered, &area).is_none());
+
+        assert_eq!(SpawnStrategy::from_name("farthest"), SpawnStrategy::Farthest);
+        assert_eq!(SpawnStrategy::from_name("mass"), SpawnStrategy::MassBlob);
+    }
+}
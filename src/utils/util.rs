@@ -1,10 +1,10 @@
-use crate::map::cell::Cell;
-use crate::map::player::Player;
+use crate::config::get_current_config;
 use crate::map::point::Point;
 use chrono::Utc;
 use lazy_static::lazy_static;
 use rand::distributions::uniform::{SampleRange, SampleUniform};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use regex::Regex;
 use std::f32::consts::PI;
 
@@ -87,8 +87,12 @@ pub fn create_random_position_in_range(max_x: f32, max_y: f32) -> Point {
     }
 }
 
-pub fn create_random_number_u32(from: u16, to: u16) -> u16 {
-    rand::thread_rng().gen_range(from..to)
+// seeded when a seed is given, from the os entropy otherwise
+pub fn create_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
 }
 
 pub fn create_random_position(
@@ -111,25 +115,30 @@ pub fn create_random_position(
 
 //generates a random point to use its x and y values and know a position on the map
 fn random_position(radius: f32) -> Point {
+    let config = get_current_config();
     Point {
-        x: random_in_range(radius..15000.0 - radius),
-        y: random_in_range(radius..15000.0 - radius),
+        x: random_in_range(radius..config.game_width as f32 - radius),
+        y: random_in_range(radius..config.game_height as f32 - radius),
         radius,
     }
 }
 
 //makes sure that the posiiton is not below a player, used to determine the spawning point of a player in accordance to the rest of the players
 pub fn uniform_position(points: &[Point], radius: f32) -> Point {
+    farthest_candidate(points, || random_position(radius))
+}
+
+fn farthest_candidate(points: &[Point], create_candidate: impl Fn() -> Point) -> Point {
     if points.is_empty() {
-        return random_position(radius);
+        return create_candidate();
     }
 
     let number_of_candidates = 10;
     let mut max_distance = 0.0;
-    let mut best_candidate = random_position(radius);
+    let mut best_candidate = create_candidate();
 
     for _ in 0..number_of_candidates {
-        let candidate = random_position(radius);
+        let candidate = create_candidate();
         let mut min_distance = f32::INFINITY;
 
         for point in points.iter() {
@@ -148,105 +157,7 @@ pub fn uniform_position(points: &[Point], radius: f32) -> Point {
     best_candidate
 }
 
-//checks which cell ate the other one by knowing which one is bigger, if there is an overlap between the cells
-pub fn check_who_ate_who(cell_a: &Cell, cell_b: &Cell) -> u8 {
-    if check_overlap(&cell_a.position, &cell_b.position) {
-        let min_cell_rad = f32::min(cell_a.position.radius, cell_b.position.radius);
-        if min_cell_rad == cell_a.position.radius {
-            // Since we're working with integers, compare cell_a.mass to 5/6 of cell_b.mass instead of multiplying
-            if cell_b.mass > (cell_a.mass * 6) / 5 {
-                return 2;
-            }
-        } else {
-            // Similar comparison but for cell_b against cell_a
-            if cell_a.mass > (cell_b.mass * 6) / 6 {
-                return 1;
-            }
-        }
-    }
-    return 0;
-}
-
-
-//Rework
-pub fn is_visible_entity(position_a: Point, player: &Player) -> bool {
-    return test_rectangle_rectangle(
-        position_a.x,
-        position_a.y,
-        position_a.radius,
-        player.x,
-        player.y,
-        player.screen_width / player.ratio,
-        player.screen_height / player.ratio,
-    );
-}
-
-fn test_rectangle_rectangle(
-    center_x_a: f32,
-    center_y_a: f32,
-    radius: f32,
-    center_x_b: f32,
-    center_y_b: f32,
-    width_b: f32,
-    height_b: f32,
-) -> bool {
-    let half_width_a = radius;
-    let half_height_a = radius;
-    let half_width_b = width_b / 2.0;
-    let half_height_b = height_b / 2.0;
-
-    center_x_a + half_width_a > center_x_b - half_width_b
-        && center_x_a - half_width_a < center_x_b + half_width_b
-        && center_y_a + half_height_a > center_y_b - half_height_b
-        && center_y_a - half_height_a < center_y_b + half_height_b
-}
-
 pub fn are_colliding(cell1: &Point, cell2: &Point) -> bool {
     // Simple collision detection logic (circle-circle collision)
-    let dx = cell1.x - cell2.x;
-    let dy = cell1.y - cell2.y;
-
-    let distance = (dx * dx + dy * dy).sqrt();
-
-    distance < (cell1.radius + cell2.radius)
-}
-
-//returns true if a cell is covering more than 60% of another cell
-pub fn check_overlap(circle_a: &Point, circle_b: &Point) -> bool {
-    let dx = circle_a.x - circle_b.x;
-    let dy = circle_a.y - circle_b.y;
-    let distance = f32::sqrt(dx * dx + dy * dy);
-
-    let r1 = circle_a.radius;
-    let r2 = circle_b.radius;
-    let r_min = r1.min(r2);
-    let r_max = r1.max(r2);
-
-    // Check for complete containment
-    if distance + r_min <= r_max {
-        return true; // One circle is completely inside the other
-    }
-
-    // Calculate intersection area if circles are partially overlapping
-    if distance < r1 + r2 && distance > f32::abs(r1 - r2) {
-        let angle1 = f32::acos((distance * distance + r1 * r1 - r2 * r2) / (2.0 * distance * r1));
-        let angle2 = f32::acos((distance * distance + r2 * r2 - r1 * r1) / (2.0 * distance * r2));
-        let part1 = r1 * r1 * angle1;
-        let part2 = r2 * r2 * angle2;
-        let part3 = 0.5
-            * f32::sqrt(
-                (-distance + r1 + r2)
-                    * (distance + r1 - r2)
-                    * (distance - r1 + r2)
-                    * (distance + r1 + r2),
-            );
-        let intersection_area = part1 + part2 - part3;
-
-        // Check if the intersection area is at least 60% of the area of the smaller circle
-        if intersection_area >= 0.6 * PI * r_min * r_min {
-            return true;
-        }
-    }
-
-    false
+    cell1.distance(cell2) < (cell1.radius + cell2.radius)
 }